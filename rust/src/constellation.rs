@@ -0,0 +1,979 @@
+use std::collections::{HashSet, VecDeque};
+use std::slice;
+use std::thread;
+
+use crate::unionfind::SparseUnionFind;
+use crate::{Coordinate, Error, Grid, Metric, Point, Result, Stats, Summary, UnionFind, Wide};
+
+/// The distance, measured by `metric`, within which two points belong to the
+/// same constellation.
+///
+/// The default connects points at most 3 apart in Manhattan distance.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Threshold<T = i64> {
+    /// The largest distance at which two points are connected.
+    pub distance: T,
+    /// When set, points exactly `distance` apart are not connected.
+    pub strict: bool,
+    /// How the distance between two points is measured.
+    pub metric: Metric,
+    /// How far a distance can be from `distance` while still being treated
+    /// as equal to it. This is meant to absorb rounding errors in
+    /// floating-point distances, and should not be negative.
+    ///
+    /// Points are connected if their distance is at most
+    /// `distance + tolerance`, or with `strict`, if it is less than
+    /// `distance - tolerance`.
+    pub tolerance: T,
+}
+
+impl<T: Coordinate> Threshold<T> {
+    /// Return true if `a` and `b` are close enough to be connected.
+    ///
+    /// This is exact as long as `check` succeeds. Distances too large to
+    /// compute are never within a threshold that can be computed.
+    pub fn connects(&self, a: &Point<T>, b: &Point<T>) -> bool {
+        let limit = self.raw_limit().unwrap_or(T::Wide::MAX);
+        let Some(distance) = self.metric.raw_distance(a, b) else {
+            return false;
+        };
+        if self.strict {
+            distance < limit
+        } else {
+            distance <= limit
+        }
+    }
+
+    /// The largest difference on a single axis between two connected points.
+    pub fn axis_radius(&self) -> T {
+        self.metric
+            .axis_radius(self.distance.saturating_add(self.tolerance))
+    }
+
+    /// Return an error if this threshold is too large to compare distances
+    /// against exactly, which can only happen with the Minkowski metric.
+    pub fn check(&self) -> Result<()> {
+        match self.raw_limit() {
+            Some(_) => Ok(()),
+            None => Err(Error::Overflow(format!(
+                "the threshold {} in the {} metric",
+                self.distance, self.metric
+            ))),
+        }
+    }
+
+    /// Return the distance, adjusted by the tolerance, in raw form.
+    fn raw_limit(&self) -> Option<T::Wide> {
+        let (distance, tolerance) = (self.distance.widen(), self.tolerance.widen());
+        let limit = if self.strict {
+            distance.checked_sub(tolerance)?
+        } else {
+            distance.checked_add(tolerance)?
+        };
+        self.metric.raw_power(limit)
+    }
+}
+
+impl<T: Coordinate> Default for Threshold<T> {
+    fn default() -> Threshold<T> {
+        Threshold {
+            distance: T::from(3),
+            strict: false,
+            metric: Metric::Manhattan,
+            tolerance: T::from(0),
+        }
+    }
+}
+
+/// A partition of a set of points into constellations.
+///
+/// Constellations are kept in a canonical order, so that the same points
+/// always give the same result no matter the order they were given in. Within
+/// a constellation, points are sorted, with duplicate points ordered by their
+/// position in the input. Constellations are then sorted by their first point.
+/// The index of a constellation in this order serves as its id.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constellations<T = i64> {
+    groups: Vec<Constellation<T>>,
+    threshold: Threshold<T>,
+}
+
+impl<T: Coordinate> Constellations<T> {
+    /// Group `points` into constellations under `threshold`.
+    pub fn cluster(points: &[Point<T>], threshold: Threshold<T>) -> Constellations<T> {
+        let mut clusterer = Clusterer::new(threshold);
+        for p in points {
+            clusterer.push(p.clone());
+        }
+        clusterer.finish()
+    }
+
+    /// Group `points` into constellations under `threshold`, using up to
+    /// `threads` threads. With `threads` set to 0, one thread is used per
+    /// available CPU.
+    ///
+    /// The result is exactly the same as `cluster`'s. Each thread finds the
+    /// neighbors of a share of the points and records which pairs joined two
+    /// of its sets, and those pairs are then merged on the calling thread.
+    /// A thread's sets only hold its own points and their neighbors, so the
+    /// memory it needs grows with its share rather than with all the points.
+    pub fn cluster_parallel(
+        points: &[Point<T>],
+        threshold: Threshold<T>,
+        threads: usize,
+    ) -> Constellations<T> {
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let threads = threads.min(points.len() / 1024 + 1);
+        if threads <= 1 {
+            return Constellations::cluster(points, threshold);
+        }
+
+        let mut grid = Grid::new(threshold.axis_radius());
+        for (i, p) in points.iter().enumerate() {
+            grid.insert(i, p);
+        }
+        let chunk = points.len().div_ceil(threads);
+        let links: Vec<Vec<(usize, usize)>> = thread::scope(|scope| {
+            let grid = &grid;
+            let handles: Vec<_> = points
+                .chunks(chunk)
+                .enumerate()
+                .map(|(t, share)| {
+                    scope.spawn(move || {
+                        let mut uf = SparseUnionFind::default();
+                        let mut links = vec![];
+                        for (i, p) in share.iter().enumerate() {
+                            let i = t * chunk + i;
+                            grid.for_each_candidate(p, threshold.axis_radius(), |j| {
+                                if j < i && threshold.connects(p, &points[j]) && uf.union(i, j) {
+                                    links.push((i, j));
+                                }
+                            });
+                        }
+                        links
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut uf = UnionFind::new(points.len());
+        for (i, j) in links.into_iter().flatten() {
+            uf.union(i, j);
+        }
+        Clusterer {
+            threshold,
+            points: points.to_vec(),
+            grid,
+            uf,
+            removed: vec![false; points.len()],
+            num_removed: 0,
+        }
+        .finish()
+    }
+
+    /// Return the constellations found.
+    pub fn groups(&self) -> &[Constellation<T>] {
+        &self.groups
+    }
+
+    /// Return the number of constellations found.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Return true if there are no constellations, i.e., there were no points.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Return the threshold these constellations were found with.
+    pub fn threshold(&self) -> Threshold<T> {
+        self.threshold
+    }
+
+    /// Put the constellations into canonical order.
+    fn canonicalize(&mut self) {
+        for group in &mut self.groups {
+            let mut members: Vec<(Point<T>, usize)> = group
+                .points
+                .drain(..)
+                .zip(group.indices.drain(..))
+                .collect();
+            members.sort();
+            for (p, i) in members {
+                group.points.push(p);
+                group.indices.push(i);
+            }
+        }
+        self.groups
+            .sort_by(|a, b| (&a.points[0], a.indices[0]).cmp(&(&b.points[0], b.indices[0])));
+    }
+
+    /// Return the number of points in all constellations.
+    pub fn num_points(&self) -> usize {
+        self.groups.iter().map(|g| g.points.len()).sum()
+    }
+
+    /// Return the index of the constellation each point is in, in the order
+    /// the points were given.
+    pub fn labels(&self) -> Vec<usize> {
+        let mut labels = vec![0; self.num_points()];
+        for (label, group) in self.groups.iter().enumerate() {
+            for &i in &group.indices {
+                labels[i] = label;
+            }
+        }
+        labels
+    }
+
+    /// Return every point along with the index of the constellation it is in,
+    /// in the order the points were given.
+    pub fn labelled(&self) -> Vec<(usize, &Point<T>)> {
+        let mut labelled = vec![None; self.num_points()];
+        for (label, group) in self.groups.iter().enumerate() {
+            for (&i, p) in group.indices.iter().zip(group.points.iter()) {
+                labelled[i] = Some((label, p));
+            }
+        }
+        labelled.into_iter().map(|pair| pair.unwrap()).collect()
+    }
+
+    /// Return statistics about these constellations. This fails if a
+    /// diameter does not fit in a coordinate.
+    pub fn stats(&self) -> Result<Stats<T>> {
+        Stats::new(self)
+    }
+
+    /// Return every point connected to `point`, along with the index of the
+    /// constellation it is in.
+    pub fn connected(&self, point: &Point<T>) -> Vec<(usize, &Point<T>)> {
+        self.connected_all(slice::from_ref(point)).pop().unwrap()
+    }
+
+    /// Return every point connected to each of `queries`, as `connected`
+    /// does. The points are indexed once and the index is shared by every
+    /// query, so this is much faster than calling `connected` in a loop.
+    pub fn connected_all(&self, queries: &[Point<T>]) -> Vec<Vec<(usize, &Point<T>)>> {
+        // Points are numbered constellation by constellation, in canonical
+        // order, so sorting the numbers found puts the points in that order.
+        let labelled: Vec<(usize, &Point<T>)> = self
+            .groups
+            .iter()
+            .enumerate()
+            .flat_map(|(i, group)| group.points.iter().map(move |p| (i, p)))
+            .collect();
+        let mut grid = Grid::new(self.threshold.axis_radius());
+        for (pos, &(_, p)) in labelled.iter().enumerate() {
+            grid.insert(pos, p);
+        }
+        queries
+            .iter()
+            .map(|query| {
+                let mut found = vec![];
+                grid.for_each_candidate(query, self.threshold.axis_radius(), |pos| {
+                    if self.threshold.connects(query, labelled[pos].1) {
+                        found.push(pos);
+                    }
+                });
+                found.sort_unstable();
+                found.into_iter().map(|pos| labelled[pos]).collect()
+            })
+            .collect()
+    }
+
+    /// Return the shortest chain of points linking the points at input
+    /// indices `from` and `to`, as input indices starting with `from` and
+    /// ending with `to`. Each point in the chain is connected to the next.
+    /// Returns `None` if the two points are in different constellations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` is not the index of a point.
+    pub fn path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let labels = self.labels();
+        if labels[from] != labels[to] {
+            return None;
+        }
+        let group = &self.groups[labels[from]];
+        let position = |index| group.indices.iter().position(|&i| i == index).unwrap();
+        let (start, end) = (position(from), position(to));
+
+        // A breadth-first search finds a chain with the fewest hops. Points
+        // are in canonical order within the group, so visiting neighbors in
+        // order of position makes the chain found deterministic.
+        let mut grid = Grid::new(self.threshold.axis_radius());
+        for (pos, p) in group.points.iter().enumerate() {
+            grid.insert(pos, p);
+        }
+        let mut previous = vec![None; group.points.len()];
+        previous[start] = Some(start);
+        let mut queue = VecDeque::from([start]);
+        while let Some(pos) = queue.pop_front() {
+            if pos == end {
+                break;
+            }
+            let mut neighbors = grid.within(&group.points, &group.points[pos], self.threshold);
+            neighbors.sort_unstable();
+            for next in neighbors {
+                if previous[next].is_none() {
+                    previous[next] = Some(pos);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut path = vec![group.indices[end]];
+        let mut pos = end;
+        while pos != start {
+            pos = previous[pos].unwrap();
+            path.push(group.indices[pos]);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Return the indices of the constellations that `point` would join if it
+    /// were added, in increasing order, without adding it. With more than
+    /// one, adding `point` would merge them all into one constellation. With
+    /// none, it would start a new one.
+    pub fn lookup(&self, point: &Point<T>) -> Vec<usize> {
+        self.lookup_all(slice::from_ref(point)).pop().unwrap()
+    }
+
+    /// Return the constellations each of `queries` would join, as `lookup`
+    /// does, sharing one index of the points between all of them.
+    pub fn lookup_all(&self, queries: &[Point<T>]) -> Vec<Vec<usize>> {
+        self.connected_all(queries)
+            .into_iter()
+            .map(|found| {
+                let mut ids: Vec<usize> = found.into_iter().map(|(id, _)| id).collect();
+                ids.dedup();
+                ids
+            })
+            .collect()
+    }
+}
+
+impl<T: Coordinate> Eq for Constellations<T> {}
+
+/// Groups points into constellations as they arrive.
+///
+/// Each point is connected to the points before it as soon as it is pushed,
+/// so points can be fed in straight from a reader without first collecting
+/// them all. The number of constellations and which points are in them can
+/// be queried after every push, without reclustering.
+///
+/// Points are identified by the order they were pushed in, starting from 0.
+/// Points can also be removed, which splits their constellation if they were
+/// the only link between its parts. Removing a point leaves the indices of
+/// the other points unchanged.
+#[derive(Clone, Debug)]
+pub struct Clusterer<T = i64> {
+    threshold: Threshold<T>,
+    points: Vec<Point<T>>,
+    grid: Grid<T>,
+    uf: UnionFind,
+    /// Which points have been removed, by index.
+    removed: Vec<bool>,
+    /// The number of points removed.
+    num_removed: usize,
+}
+
+impl<T: Coordinate> Clusterer<T> {
+    /// Create a clusterer with no points that connects points by `threshold`.
+    pub fn new(threshold: Threshold<T>) -> Clusterer<T> {
+        Clusterer {
+            threshold,
+            points: vec![],
+            grid: Grid::new(threshold.axis_radius()),
+            uf: UnionFind::new(0),
+            removed: vec![],
+            num_removed: 0,
+        }
+    }
+
+    /// Add a point, connecting it to every point already added. Returns the
+    /// index of the new point.
+    pub fn push(&mut self, point: Point<T>) -> usize {
+        let i = self.uf.push();
+        for j in self.grid.within(&self.points, &point, self.threshold) {
+            self.uf.union(i, j);
+        }
+        self.grid.insert(i, &point);
+        self.points.push(point);
+        self.removed.push(false);
+        i
+    }
+
+    /// Remove the point at `index`, splitting its constellation if need be.
+    /// Returns false if there is no such point or it was already removed.
+    ///
+    /// Only the constellation the point was in is reclustered, so this takes
+    /// time in proportion to the size of that constellation.
+    pub fn remove(&mut self, index: usize) -> bool {
+        if !self.is_live(index) {
+            return false;
+        }
+
+        let mut members = vec![index];
+        let mut seen: HashSet<usize> = members.iter().cloned().collect();
+        let mut next = 0;
+        while next < members.len() {
+            let p = &self.points[members[next]];
+            for j in self.grid.within(&self.points, p, self.threshold) {
+                if seen.insert(j) {
+                    members.push(j);
+                }
+            }
+            next += 1;
+        }
+
+        self.uf.split(&members);
+        self.grid.remove(index, &self.points[index]);
+        self.removed[index] = true;
+        self.num_removed += 1;
+        for &i in &members[1..] {
+            for j in self
+                .grid
+                .within(&self.points, &self.points[i], self.threshold)
+            {
+                self.uf.union(i, j);
+            }
+        }
+        true
+    }
+
+    /// Remove a point equal to `point`, splitting its constellation if need
+    /// be. If there are several, the one added first is removed. Returns the
+    /// index of the point removed, if any.
+    pub fn remove_point(&mut self, point: &Point<T>) -> Option<usize> {
+        let mut found = None;
+        self.grid.for_each_candidate(point, T::from(0), |i| {
+            if self.points[i] == *point && found.is_none_or(|f| i < f) {
+                found = Some(i);
+            }
+        });
+        let index = found?;
+        self.remove(index);
+        Some(index)
+    }
+
+    /// Return the number of constellations formed by the points added so far.
+    pub fn len(&self) -> usize {
+        self.uf.sets() - self.num_removed
+    }
+
+    /// Return true if there are no points, either because none were added or
+    /// because they have all been removed.
+    pub fn is_empty(&self) -> bool {
+        self.points.len() == self.num_removed
+    }
+
+    /// Return the points added so far, in the order they were added. This
+    /// includes points that have since been removed.
+    pub fn points(&self) -> &[Point<T>] {
+        &self.points
+    }
+
+    /// Return true if the point at `index` has been removed. Returns false if
+    /// there is no such point.
+    pub fn is_removed(&self, index: usize) -> bool {
+        self.removed.get(index) == Some(&true)
+    }
+
+    /// Return true if the points at indices `a` and `b` are currently in the
+    /// same constellation. Removed points, and indices no point was added
+    /// at, are in no constellation.
+    pub fn same_constellation(&mut self, a: usize, b: usize) -> bool {
+        self.is_live(a) && self.is_live(b) && self.uf.find(a) == self.uf.find(b)
+    }
+
+    /// Return the number of points in the constellation containing the point
+    /// at `index`, or 0 if there is no such point or it has been removed.
+    pub fn constellation_size(&mut self, index: usize) -> usize {
+        if !self.is_live(index) {
+            return 0;
+        }
+        self.uf.size(index)
+    }
+
+    /// Return the indices of every point in the same constellation as the
+    /// point at `index`, including `index` itself, in increasing order. This
+    /// is empty if there is no such point or it has been removed.
+    ///
+    /// This takes time in proportion to the size of the constellation.
+    pub fn members(&self, index: usize) -> Vec<usize> {
+        if !self.is_live(index) {
+            return vec![];
+        }
+        // Removing a point leaves it in a set of its own, so every member
+        // found is live.
+        let mut members = self.uf.members(index);
+        members.sort_unstable();
+        members
+    }
+
+    /// Return true if a point was added at `index` and hasn't been removed.
+    fn is_live(&self, index: usize) -> bool {
+        self.removed.get(index) == Some(&false)
+    }
+
+    /// Return the constellations formed by the points added so far, leaving
+    /// the clusterer as it is so that more points can be added.
+    pub fn snapshot(&self) -> Constellations<T> {
+        self.clone().finish()
+    }
+
+    /// Return the number of coordinates of the points added, or `None` if no
+    /// points have been added yet.
+    pub fn dimension(&self) -> Option<usize> {
+        self.points.first().map(|p| p.dimension())
+    }
+
+    /// Return the constellations formed by all of the points added.
+    ///
+    /// Removed points are left out, and the indices in the result count only
+    /// the points that remain.
+    pub fn finish(mut self) -> Constellations<T> {
+        let mut groups: Vec<Constellation<T>> = vec![];
+        let mut group_of_root = vec![usize::MAX; self.points.len()];
+        let live = self
+            .points
+            .into_iter()
+            .enumerate()
+            .filter(|&(i, _)| !self.removed[i]);
+        for (i, (id, p)) in live.enumerate() {
+            let root = self.uf.find(id);
+            if group_of_root[root] == usize::MAX {
+                group_of_root[root] = groups.len();
+                groups.push(Constellation {
+                    points: vec![],
+                    indices: vec![],
+                });
+            }
+            groups[group_of_root[root]].points.push(p);
+            groups[group_of_root[root]].indices.push(i);
+        }
+        let mut consts = Constellations {
+            groups,
+            threshold: self.threshold,
+        };
+        consts.canonicalize();
+        consts
+    }
+}
+
+// The original merge-until-fixpoint algorithm. It is cubic in the number of
+// points, but it is simple enough to be obviously correct, so the tests keep
+// it around as a reference to check `Constellations::cluster` against.
+#[cfg(test)]
+impl<T: Coordinate> Constellations<T> {
+    /// Put every point into a constellation of its own.
+    ///
+    /// Calling `step` until it returns false, followed by `canonicalize`,
+    /// yields the same constellations as `cluster`, only much more slowly.
+    fn shatter_all(points: &[Point<T>], threshold: Threshold<T>) -> Constellations<T> {
+        let mut groups = vec![];
+        for (i, p) in points.iter().enumerate() {
+            groups.push(Constellation {
+                points: vec![p.clone()],
+                indices: vec![i],
+            });
+        }
+        Constellations { groups, threshold }
+    }
+
+    /// Merge the first pair of connected constellations found. Returns false
+    /// if no two constellations are connected.
+    fn step(&mut self) -> bool {
+        for i in 0..self.groups.len() {
+            for j in i + 1..self.groups.len() {
+                if self.groups[i].is_connected(&self.groups[j], self.threshold) {
+                    self.merge(i, j);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn merge(&mut self, i1: usize, i2: usize) {
+        let g2 = self.groups.swap_remove(i2);
+        self.groups[i1].join(&g2);
+    }
+}
+
+/// A group of points linked together by chains of connected points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constellation<T = i64> {
+    points: Vec<Point<T>>,
+    indices: Vec<usize>,
+}
+
+impl<T: Coordinate> Eq for Constellation<T> {}
+
+impl<T: Coordinate> Constellation<T> {
+    /// Return the points in this constellation.
+    pub fn points(&self) -> &[Point<T>] {
+        &self.points
+    }
+
+    /// Return the position each point in this constellation had in the input,
+    /// in the same order as `points`.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Return the size and extent of this constellation. This fails if its
+    /// diameter does not fit in a coordinate.
+    pub fn summary(&self) -> Result<Summary<T>> {
+        Ok(Summary::of(&self.points)?.expect("constellations are never empty"))
+    }
+
+    #[cfg(test)]
+    fn join(&mut self, other: &Constellation<T>) {
+        self.points.extend(other.points.iter().cloned());
+        self.indices.extend(other.indices.iter().cloned());
+    }
+
+    #[cfg(test)]
+    fn is_connected(&self, other: &Constellation<T>, threshold: Threshold<T>) -> bool {
+        for p in other.points.iter() {
+            if self.is_point_connected(p, threshold) {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[cfg(test)]
+    fn is_point_connected(&self, point: &Point<T>, threshold: Threshold<T>) -> bool {
+        for p in self.points.iter() {
+            if threshold.connects(point, p) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A xorshift generator, so that the tests are repeatable and need no
+    /// dependencies.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Return a number from `lo` to `hi`, inclusive.
+        fn range(&mut self, lo: i64, hi: i64) -> i64 {
+            lo + (self.next() % (hi - lo + 1) as u64) as i64
+        }
+
+        fn points(&mut self, n: usize, dimension: usize, span: i64) -> Vec<Point> {
+            (0..n)
+                .map(|_| Point::new((0..dimension).map(|_| self.range(-span, span)).collect()))
+                .collect()
+        }
+    }
+
+    const METRICS: [Metric; 4] = [
+        Metric::Manhattan,
+        Metric::Chebyshev,
+        Metric::SquaredEuclidean,
+        Metric::Minkowski(3),
+    ];
+
+    fn reference<T: Coordinate>(points: &[Point<T>], threshold: Threshold<T>) -> Constellations<T> {
+        let mut consts = Constellations::shatter_all(points, threshold);
+        while consts.step() {}
+        consts.canonicalize();
+        consts
+    }
+
+    #[test]
+    fn cluster_matches_reference() {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        for metric in METRICS {
+            for strict in [false, true] {
+                for distance in [0, 1, 3, 6] {
+                    for dimension in [1, 2, 4] {
+                        let points = rng.points(60, dimension, 6);
+                        let threshold = Threshold {
+                            distance,
+                            strict,
+                            metric,
+                            tolerance: 0,
+                        };
+                        assert_eq!(
+                            Constellations::cluster(&points, threshold),
+                            reference(&points, threshold),
+                            "{:?}",
+                            threshold
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cluster_matches_reference_with_tolerance() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+        for metric in METRICS {
+            for strict in [false, true] {
+                for tolerance in [0.0, 1e-9, 0.05] {
+                    let points: Vec<Point<f64>> = (0..60)
+                        .map(|_| {
+                            Point::new((0..3).map(|_| rng.range(-30, 30) as f64 / 10.0).collect())
+                        })
+                        .collect();
+                    let threshold = Threshold {
+                        distance: 0.3,
+                        strict,
+                        metric,
+                        tolerance,
+                    };
+                    assert_eq!(
+                        Constellations::cluster(&points, threshold),
+                        reference(&points, threshold),
+                        "{:?}",
+                        threshold
+                    );
+                }
+            }
+        }
+    }
+
+    fn line(xs: &[i64]) -> Vec<Point> {
+        xs.iter().map(|&x| Point::new(vec![x])).collect()
+    }
+
+    fn remaining(clusterer: &Clusterer) -> Vec<Point> {
+        let points = clusterer.points().iter().enumerate();
+        points
+            .filter(|&(i, _)| !clusterer.is_removed(i))
+            .map(|(_, p)| p.clone())
+            .collect()
+    }
+
+    #[test]
+    fn path_takes_the_shortcut() {
+        let points = line(&[6, 0, 3, 1, 5, 2, 4, 20]);
+        let consts = Constellations::cluster(&points, Threshold::default());
+        assert_eq!(consts.path(1, 0), Some(vec![1, 2, 0]));
+        assert_eq!(consts.path(0, 1), Some(vec![0, 2, 1]));
+        assert_eq!(consts.path(3, 5), Some(vec![3, 5]));
+        assert_eq!(consts.path(4, 4), Some(vec![4]));
+        assert_eq!(consts.path(7, 7), Some(vec![7]));
+        assert_eq!(consts.path(1, 7), None);
+    }
+
+    #[test]
+    fn path_is_a_shortest_chain() {
+        let mut rng = Rng(0x6a09e667f3bcc909);
+        for metric in METRICS {
+            let threshold = Threshold {
+                distance: 2,
+                strict: false,
+                metric,
+                tolerance: 0,
+            };
+            let points = rng.points(50, 2, 7);
+            let consts = Constellations::cluster(&points, threshold);
+            let labels = consts.labels();
+            for from in [0, 17, 33] {
+                // Hop counts from `from` to every point, by a breadth-first
+                // search that checks every pair.
+                let mut hops = vec![None; points.len()];
+                hops[from] = Some(0);
+                let mut queue = VecDeque::from([from]);
+                while let Some(i) = queue.pop_front() {
+                    for j in 0..points.len() {
+                        if hops[j].is_none() && threshold.connects(&points[i], &points[j]) {
+                            hops[j] = Some(hops[i].unwrap() + 1);
+                            queue.push_back(j);
+                        }
+                    }
+                }
+                for to in 0..points.len() {
+                    let Some(path) = consts.path(from, to) else {
+                        assert_ne!(labels[from], labels[to]);
+                        assert_eq!(hops[to], None);
+                        continue;
+                    };
+                    assert_eq!((path[0], path[path.len() - 1]), (from, to));
+                    assert_eq!(Some(path.len() - 1), hops[to], "{:?}", threshold);
+                    for hop in path.windows(2) {
+                        assert!(threshold.connects(&points[hop[0]], &points[hop[1]]));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn membership_between_pushes() {
+        let mut rng = Rng(0x94d049bb133111eb);
+        for metric in METRICS {
+            let threshold = Threshold {
+                distance: 2,
+                strict: false,
+                metric,
+                tolerance: 0,
+            };
+            let points = rng.points(60, 2, 8);
+            let mut clusterer = Clusterer::new(threshold);
+            for (n, p) in points.iter().enumerate() {
+                assert_eq!(clusterer.push(p.clone()), n);
+                let consts = Constellations::cluster(&points[..=n], threshold);
+                let labels = consts.labels();
+                for i in [0, n / 2, n] {
+                    let mut expected = consts.groups()[labels[i]].indices().to_vec();
+                    expected.sort_unstable();
+                    assert_eq!(clusterer.members(i), expected, "{:?}", threshold);
+                    assert_eq!(clusterer.constellation_size(i), expected.len());
+                    for j in [0, n / 3, n] {
+                        assert_eq!(clusterer.same_constellation(i, j), labels[i] == labels[j]);
+                    }
+                }
+                assert_eq!(clusterer.len(), consts.len());
+            }
+            assert_eq!(clusterer.members(points.len()), Vec::<usize>::new());
+            assert_eq!(clusterer.constellation_size(points.len()), 0);
+            assert!(!clusterer.same_constellation(0, points.len()));
+        }
+    }
+
+    #[test]
+    fn remove_bridge_point_splits_constellation() {
+        let mut clusterer = Clusterer::new(Threshold::default());
+        for p in line(&[0, 3, 6, 20]) {
+            clusterer.push(p);
+        }
+        assert_eq!(clusterer.len(), 2);
+        assert_eq!(clusterer.members(0), vec![0, 1, 2]);
+
+        assert!(clusterer.remove(1));
+        assert_eq!(clusterer.len(), 3);
+        assert!(!clusterer.same_constellation(0, 2));
+        assert!(!clusterer.same_constellation(0, 1));
+        assert_eq!(clusterer.members(0), vec![0]);
+        assert_eq!(clusterer.members(1), Vec::<usize>::new());
+        assert_eq!(clusterer.constellation_size(1), 0);
+        assert_eq!(
+            clusterer.snapshot(),
+            Constellations::cluster(&line(&[0, 6, 20]), Threshold::default())
+        );
+    }
+
+    #[test]
+    fn remove_twice() {
+        let mut clusterer = Clusterer::new(Threshold::default());
+        for p in line(&[0, 1, 2]) {
+            clusterer.push(p);
+        }
+        assert!(clusterer.remove(2));
+        assert!(!clusterer.remove(2));
+        assert!(!clusterer.remove(3));
+        assert_eq!(clusterer.len(), 1);
+        assert_eq!(clusterer.constellation_size(0), 2);
+        assert!(clusterer.is_removed(2));
+        assert!(!clusterer.is_removed(3));
+
+        assert!(clusterer.remove(0));
+        assert!(clusterer.remove(1));
+        assert_eq!(clusterer.len(), 0);
+        assert!(clusterer.is_empty());
+        assert!(clusterer.snapshot().is_empty());
+    }
+
+    #[test]
+    fn remove_point_with_duplicates() {
+        let mut clusterer = Clusterer::new(Threshold::default());
+        for p in line(&[2, 0, 9, 0, 0]) {
+            clusterer.push(p);
+        }
+        let zero = Point::new(vec![0]);
+        assert_eq!(clusterer.remove_point(&zero), Some(1));
+        assert_eq!(clusterer.remove_point(&zero), Some(3));
+        assert_eq!(clusterer.members(4), vec![0, 4]);
+        assert_eq!(clusterer.remove_point(&zero), Some(4));
+        assert_eq!(clusterer.remove_point(&zero), None);
+        assert_eq!(clusterer.remove_point(&Point::new(vec![7])), None);
+        assert_eq!(clusterer.len(), 2);
+    }
+
+    #[test]
+    fn remove_matches_reclustering() {
+        let mut rng = Rng(0xd1b54a32d192ed03);
+        for metric in METRICS {
+            for dimension in [1, 2, 3] {
+                let threshold = Threshold {
+                    distance: 2,
+                    strict: false,
+                    metric,
+                    tolerance: 0,
+                };
+                let points = rng.points(80, dimension, 8);
+                let mut clusterer = Clusterer::new(threshold);
+                for p in &points {
+                    clusterer.push(p.clone());
+                }
+                for _ in 0..60 {
+                    let index = rng.range(0, points.len() as i64 - 1) as usize;
+                    let was_removed = clusterer.is_removed(index);
+                    assert_eq!(clusterer.remove(index), !was_removed);
+
+                    let expected = Constellations::cluster(&remaining(&clusterer), threshold);
+                    assert_eq!(clusterer.len(), expected.len(), "{:?}", threshold);
+                    assert_eq!(clusterer.snapshot(), expected, "{:?}", threshold);
+                    for i in 0..points.len() {
+                        let members = clusterer.members(i);
+                        assert_eq!(members.len(), clusterer.constellation_size(i));
+                        assert!(members.iter().all(|&j| clusterer.same_constellation(i, j)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cluster_parallel_matches_cluster() {
+        let mut rng = Rng(0xbf58476d1ce4e5b9);
+        // Enough points that every thread gets a share.
+        let points = rng.points(5000, 3, 30);
+        for metric in METRICS {
+            for distance in [1, 3] {
+                let threshold = Threshold {
+                    distance,
+                    strict: false,
+                    metric,
+                    tolerance: 0,
+                };
+                let expected = Constellations::cluster(&points, threshold);
+                for threads in [2, 4] {
+                    assert_eq!(
+                        Constellations::cluster_parallel(&points, threshold, threads),
+                        expected,
+                        "{:?} on {} threads",
+                        threshold,
+                        threads
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::{Error, Result};
+
+/// A type that points can have as coordinates: `i64` or `f64`.
+///
+/// Integer coordinates are exact. Distances between them are computed in 128
+/// bits, and any result that does not fit is reported as an error rather than
+/// silently wrapping.
+///
+/// Floating-point coordinates are always finite, which lets points be ordered
+/// and hashed. Distances between them are subject to rounding, which a
+/// `Threshold`'s tolerance can absorb.
+///
+/// This trait is sealed, so it cannot be implemented outside this crate.
+pub trait Coordinate:
+    Copy + fmt::Debug + fmt::Display + PartialOrd + From<i32> + Send + Sync + sealed::Sealed
+{
+    /// The type distances are computed in: `i128` for `i64` coordinates, and
+    /// `f64` for `f64` coordinates.
+    type Wide: Wide;
+
+    /// Parse a coordinate from `field`, which starts at `column` of line 1.
+    fn parse_field(column: usize, field: &str) -> Result<Self>;
+
+    /// Return this coordinate in its canonical form, or `None` if it is not
+    /// finite. The only coordinate that changes is `-0.0`, which becomes
+    /// `0.0`.
+    fn canonical(self) -> Option<Self>;
+
+    /// Compare two canonical coordinates. Unlike `partial_cmp`, this is a
+    /// total order.
+    fn total_cmp(&self, other: &Self) -> Ordering;
+
+    /// Feed this coordinate into `state`. Equal canonical coordinates hash
+    /// the same.
+    fn hash_into<H: Hasher>(&self, state: &mut H);
+
+    /// Convert this coordinate into the type distances are computed in.
+    fn widen(self) -> Self::Wide;
+
+    /// Convert a value computed in the wide type back into a coordinate, or
+    /// return `None` if it does not fit.
+    fn narrow(wide: Self::Wide) -> Option<Self>;
+
+    /// Add two coordinates, saturating at the largest and smallest values.
+    fn saturating_add(self, other: Self) -> Self;
+
+    /// Subtract two coordinates, saturating at the largest and smallest
+    /// values.
+    fn saturating_sub(self, other: Self) -> Self;
+
+    /// Return the largest coordinate whose square is at most this one, or 0
+    /// if this is negative.
+    fn sqrt_floor(self) -> Self;
+
+    /// Return the coordinate nearest `value`, saturating at the largest and
+    /// smallest coordinates.
+    fn approximate(value: f64) -> Self;
+
+    /// Return a positive grid cell width close to this one.
+    fn cell_size(self) -> Self;
+
+    /// Return the grid cell this coordinate is in, when cells are `size`
+    /// wide.
+    fn cell(self, size: Self) -> i64;
+}
+
+/// Arithmetic on the type distances are computed in.
+///
+/// Every operation returns `None` instead of overflowing. For `f64`, this
+/// means returning `None` instead of a result that is not finite.
+///
+/// This trait is sealed, so it cannot be implemented outside this crate.
+pub trait Wide: Copy + fmt::Debug + fmt::Display + PartialOrd + sealed::Sealed {
+    /// Zero.
+    const ZERO: Self;
+    /// The largest value.
+    const MAX: Self;
+
+    /// Return `self + other`.
+    fn checked_add(self, other: Self) -> Option<Self>;
+    /// Return `self - other`.
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    /// Return `self * other`.
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    /// Return `self` raised to the power `exp`.
+    fn checked_pow(self, exp: u32) -> Option<Self>;
+    /// Return the absolute value of `self`.
+    fn abs(self) -> Self;
+    /// Convert `self` to the nearest `f64`.
+    fn to_f64(self) -> f64;
+}
+
+impl Coordinate for i64 {
+    type Wide = i128;
+
+    fn parse_field(column: usize, field: &str) -> Result<i64> {
+        field
+            .parse()
+            .map_err(|err| Error::parse_int(column, field, err))
+    }
+
+    fn canonical(self) -> Option<i64> {
+        Some(self)
+    }
+
+    fn total_cmp(&self, other: &i64) -> Ordering {
+        self.cmp(other)
+    }
+
+    fn hash_into<H: Hasher>(&self, state: &mut H) {
+        self.hash(state)
+    }
+
+    fn widen(self) -> i128 {
+        self as i128
+    }
+
+    fn narrow(wide: i128) -> Option<i64> {
+        wide.try_into().ok()
+    }
+
+    fn saturating_add(self, other: i64) -> i64 {
+        i64::saturating_add(self, other)
+    }
+
+    fn saturating_sub(self, other: i64) -> i64 {
+        i64::saturating_sub(self, other)
+    }
+
+    fn sqrt_floor(self) -> i64 {
+        let n = self.max(0) as i128;
+        let mut r = (n as f64).sqrt() as i128;
+        while r > 0 && r * r > n {
+            r -= 1;
+        }
+        while (r + 1) * (r + 1) <= n {
+            r += 1;
+        }
+        r as i64
+    }
+
+    fn approximate(value: f64) -> i64 {
+        // Casts from floats saturate.
+        value.round() as i64
+    }
+
+    fn cell_size(self) -> i64 {
+        self.max(1)
+    }
+
+    fn cell(self, size: i64) -> i64 {
+        self.div_euclid(size)
+    }
+}
+
+impl Coordinate for f64 {
+    type Wide = f64;
+
+    fn parse_field(column: usize, field: &str) -> Result<f64> {
+        let coord: f64 = field.parse().map_err(|err| Error::InvalidFloat {
+            line: 1,
+            column,
+            field: field.to_string(),
+            err,
+        })?;
+        coord.canonical().ok_or_else(|| Error::NotFinite {
+            line: 1,
+            column,
+            field: field.to_string(),
+        })
+    }
+
+    fn canonical(self) -> Option<f64> {
+        if self.is_finite() {
+            // Adding zero turns -0.0 into 0.0 and leaves everything else be.
+            Some(self + 0.0)
+        } else {
+            None
+        }
+    }
+
+    fn total_cmp(&self, other: &f64) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+
+    fn hash_into<H: Hasher>(&self, state: &mut H) {
+        self.to_bits().hash(state)
+    }
+
+    fn widen(self) -> f64 {
+        self
+    }
+
+    fn narrow(wide: f64) -> Option<f64> {
+        wide.canonical()
+    }
+
+    fn saturating_add(self, other: f64) -> f64 {
+        (self + other).clamp(f64::MIN, f64::MAX)
+    }
+
+    fn saturating_sub(self, other: f64) -> f64 {
+        (self - other).clamp(f64::MIN, f64::MAX)
+    }
+
+    fn sqrt_floor(self) -> f64 {
+        self.max(0.0).sqrt()
+    }
+
+    fn approximate(value: f64) -> f64 {
+        if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(f64::MIN, f64::MAX) + 0.0
+        }
+    }
+
+    fn cell_size(self) -> f64 {
+        if self > 0.0 {
+            self
+        } else {
+            1.0
+        }
+    }
+
+    fn cell(self, size: f64) -> i64 {
+        (self / size).floor() as i64
+    }
+}
+
+impl Wide for i128 {
+    const ZERO: i128 = 0;
+    const MAX: i128 = i128::MAX;
+
+    fn checked_add(self, other: i128) -> Option<i128> {
+        i128::checked_add(self, other)
+    }
+
+    fn checked_sub(self, other: i128) -> Option<i128> {
+        i128::checked_sub(self, other)
+    }
+
+    fn checked_mul(self, other: i128) -> Option<i128> {
+        i128::checked_mul(self, other)
+    }
+
+    fn checked_pow(self, exp: u32) -> Option<i128> {
+        i128::checked_pow(self, exp)
+    }
+
+    fn abs(self) -> i128 {
+        i128::abs(self)
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Wide for f64 {
+    const ZERO: f64 = 0.0;
+    const MAX: f64 = f64::MAX;
+
+    fn checked_add(self, other: f64) -> Option<f64> {
+        Some(self + other).filter(|x| x.is_finite())
+    }
+
+    fn checked_sub(self, other: f64) -> Option<f64> {
+        Some(self - other).filter(|x| x.is_finite())
+    }
+
+    fn checked_mul(self, other: f64) -> Option<f64> {
+        Some(self * other).filter(|x| x.is_finite())
+    }
+
+    fn checked_pow(self, exp: u32) -> Option<f64> {
+        Some(self.powf(exp as f64)).filter(|x| x.is_finite())
+    }
+
+    fn abs(self) -> f64 {
+        f64::abs(self)
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for i64 {}
+    impl Sealed for i128 {}
+    impl Sealed for f64 {}
+}
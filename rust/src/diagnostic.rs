@@ -0,0 +1,138 @@
+use std::fmt;
+
+use crate::Error;
+
+/// A rustc-style rendering of an error, pointing at the offending part of the
+/// line it came from.
+///
+/// ```text
+/// error: invalid integer "x"
+///  --> points.txt:3:5
+///   |
+/// 3 | 1,2,x,4
+///   |     ^
+/// ```
+///
+/// Errors without a position are rendered as a single `error:` line.
+#[derive(Debug)]
+pub struct Diagnostic<'a> {
+    error: &'a Error,
+    path: &'a str,
+    line: &'a str,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// Create a diagnostic for `error`, which occurred while reading the file
+    /// at `path`. `line` is the text of the line the error is on.
+    pub fn new(error: &'a Error, path: &'a str, line: &'a str) -> Diagnostic<'a> {
+        Diagnostic { error, path, line }
+    }
+
+    /// Return the number of characters the caret line should underline.
+    fn span(&self, column: usize) -> usize {
+        let rest = self.line.chars().count().saturating_sub(column - 1);
+        let len = match *self.error {
+            Error::Arity {
+                expected, found, ..
+            } if found > expected => rest,
+            Error::InvalidInt { ref field, .. }
+            | Error::OutOfRange { ref field, .. }
+            | Error::InvalidFloat { ref field, .. }
+            | Error::NotFinite { ref field, .. } => field.chars().count(),
+            _ => 1,
+        };
+        len.max(1)
+    }
+}
+
+impl<'a> fmt::Display for Diagnostic<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error: ")?;
+        self.error.write_message(f)?;
+        writeln!(f)?;
+
+        let (line, column) = match self.error.position() {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+        let gutter = " ".repeat(line.to_string().len());
+        writeln!(f, "{}--> {}:{}:{}", gutter, self.path, line, column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", line, self.line)?;
+        writeln!(
+            f,
+            "{} | {}{}",
+            gutter,
+            " ".repeat(column - 1),
+            "^".repeat(self.span(column))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point, PointReader, Syntax};
+
+    /// Render the diagnostic for every invalid line in `input`.
+    fn diagnostics(input: &str, syntax: Syntax) -> Vec<String> {
+        let mut reader: PointReader<&[u8]> = PointReader::new(input.as_bytes(), syntax);
+        let mut rendered = vec![];
+        while let Some(result) = reader.next() {
+            if let Err(err) = result {
+                rendered.push(Diagnostic::new(&err, "p.txt", reader.line()).to_string());
+            }
+        }
+        rendered
+    }
+
+    #[test]
+    fn rendering() {
+        let input = "1,2,3\n1,2,x\n1,2\n1,2,3,4,5\n1,2,99999999999999999999\n";
+        let expected = [
+            "error: invalid integer \"x\"\n --> p.txt:2:5\n  |\n2 | 1,2,x\n  |     ^\n",
+            "error: expected 3 coordinates, found 2\n --> p.txt:3:4\n  |\n3 | 1,2\n  |    ^\n",
+            "error: expected 3 coordinates, found 5\n --> p.txt:4:7\n  |\n4 | 1,2,3,4,5\n  \
+             |       ^^^\n",
+            "error: coordinate 99999999999999999999 is out of range\n --> p.txt:5:5\n  |\n\
+             5 | 1,2,99999999999999999999\n  |     ^^^^^^^^^^^^^^^^^^^^\n",
+        ];
+        assert_eq!(diagnostics(input, Syntax::Strict), expected);
+    }
+
+    #[test]
+    fn rendering_lenient() {
+        let input = "1 2 3 # ok\n1  2 # short\n1;; 3\n";
+        let expected = [
+            "error: expected 3 coordinates, found 2\n --> p.txt:2:5\n  |\n\
+             2 | 1  2 # short\n  |     ^\n",
+            "error: invalid integer \"\"\n --> p.txt:3:3\n  |\n3 | 1;; 3\n  |   ^\n",
+        ];
+        assert_eq!(diagnostics(input, Syntax::Lenient), expected);
+    }
+
+    #[test]
+    fn rendering_wide_gutter() {
+        let err = "1,2,y".parse::<Point>().unwrap_err();
+        let err = match err {
+            Error::InvalidInt {
+                column, field, err, ..
+            } => Error::InvalidInt {
+                line: 120,
+                column,
+                field,
+                err,
+            },
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(
+            Diagnostic::new(&err, "p.txt", "1,2,y").to_string(),
+            "error: invalid integer \"y\"\n   --> p.txt:120:5\n    |\n120 | 1,2,y\n    |     ^\n"
+        );
+        let err = Error::Overflow("a diameter".to_string());
+        assert_eq!(
+            Diagnostic::new(&err, "p.txt", "").to_string(),
+            "error: overflow computing a diameter\n"
+        );
+    }
+}
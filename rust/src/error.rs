@@ -0,0 +1,181 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::num::{IntErrorKind, ParseFloatError, ParseIntError};
+
+/// An error that can occur while reading points or configuring clustering.
+///
+/// Line and column numbers are 1-based, with columns counted in characters.
+/// Errors from `Point::from_str` report line 1, since the parser has no way of
+/// knowing where its input came from.
+#[derive(Debug)]
+pub enum Error {
+    /// A point had a different number of coordinates than the points before
+    /// it. `column` is where the first missing or surplus coordinate is.
+    Arity {
+        /// The line the point is on.
+        line: usize,
+        /// The column the problem starts at.
+        column: usize,
+        /// The number of coordinates of the points before this one.
+        expected: usize,
+        /// The number of coordinates this point has.
+        found: usize,
+    },
+    /// A coordinate was not an integer.
+    InvalidInt {
+        /// The line the coordinate is on.
+        line: usize,
+        /// The column the coordinate starts at.
+        column: usize,
+        /// The text that failed to parse.
+        field: String,
+        /// The underlying parse error.
+        err: ParseIntError,
+    },
+    /// A coordinate was an integer, but too large or small to represent.
+    OutOfRange {
+        /// The line the coordinate is on.
+        line: usize,
+        /// The column the coordinate starts at.
+        column: usize,
+        /// The text that failed to parse.
+        field: String,
+        /// The underlying parse error.
+        err: ParseIntError,
+    },
+    /// A coordinate was not a number.
+    InvalidFloat {
+        /// The line the coordinate is on.
+        line: usize,
+        /// The column the coordinate starts at.
+        column: usize,
+        /// The text that failed to parse.
+        field: String,
+        /// The underlying parse error.
+        err: ParseFloatError,
+    },
+    /// A coordinate was NaN or infinite, or too large to be represented as
+    /// anything else.
+    NotFinite {
+        /// The line the coordinate is on.
+        line: usize,
+        /// The column the coordinate starts at.
+        column: usize,
+        /// The text of the coordinate.
+        field: String,
+    },
+    /// A metric name could not be recognized.
+    UnknownMetric(String),
+    /// A result was too large to represent. The string describes what was
+    /// being computed.
+    Overflow(String),
+    /// Reading input failed.
+    Io(io::Error),
+}
+
+impl Error {
+    /// Return the line and column this error occurred at, if it came from
+    /// parsing input.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match *self {
+            Error::Arity { line, column, .. }
+            | Error::InvalidInt { line, column, .. }
+            | Error::OutOfRange { line, column, .. }
+            | Error::InvalidFloat { line, column, .. }
+            | Error::NotFinite { line, column, .. } => Some((line, column)),
+            Error::UnknownMetric(_) | Error::Overflow(_) | Error::Io(_) => None,
+        }
+    }
+
+    pub(crate) fn parse_int(column: usize, field: &str, err: ParseIntError) -> Error {
+        let field = field.to_string();
+        match *err.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => Error::OutOfRange {
+                line: 1,
+                column,
+                field,
+                err,
+            },
+            _ => Error::InvalidInt {
+                line: 1,
+                column,
+                field,
+                err,
+            },
+        }
+    }
+
+    /// Move an error reported by `Point::from_str` to the given line.
+    pub(crate) fn on_line(mut self, lineno: usize) -> Error {
+        match self {
+            Error::Arity { ref mut line, .. }
+            | Error::InvalidInt { ref mut line, .. }
+            | Error::OutOfRange { ref mut line, .. }
+            | Error::InvalidFloat { ref mut line, .. }
+            | Error::NotFinite { ref mut line, .. } => *line = lineno,
+            Error::UnknownMetric(_) | Error::Overflow(_) | Error::Io(_) => {}
+        }
+        self
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::InvalidInt { ref err, .. } | Error::OutOfRange { ref err, .. } => Some(err),
+            Error::InvalidFloat { ref err, .. } => Some(err),
+            Error::Io(ref err) => Some(err),
+            Error::Arity { .. }
+            | Error::NotFinite { .. }
+            | Error::UnknownMetric(_)
+            | Error::Overflow(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((line, column)) = self.position() {
+            write!(f, "line {}, column {}: ", line, column)?;
+        }
+        self.write_message(f)
+    }
+}
+
+impl Error {
+    /// Write a description of this error, without its position.
+    pub(crate) fn write_message(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Arity {
+                expected, found, ..
+            } => write!(f, "expected {} coordinates, found {}", expected, found),
+            Error::InvalidInt { ref field, .. } => {
+                write!(f, "invalid integer {:?}", field)
+            }
+            Error::OutOfRange { ref field, .. } => {
+                write!(f, "coordinate {} is out of range", field)
+            }
+            Error::InvalidFloat { ref field, .. } => {
+                write!(f, "invalid number {:?}", field)
+            }
+            Error::NotFinite { ref field, .. } => {
+                write!(f, "coordinate {} is not finite", field)
+            }
+            Error::UnknownMetric(ref name) => write!(
+                f,
+                "unrecognized metric {:?} (expected manhattan, chebyshev, \
+                 squared-euclidean or minkowski:P)",
+                name
+            ),
+            Error::Overflow(ref what) => write!(f, "overflow computing {}", what),
+            Error::Io(_) => write!(f, "I/O error"),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
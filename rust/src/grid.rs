@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use crate::{Coordinate, Point, Threshold};
+
+type Cell = Vec<i64>;
+
+/// A spatial index that buckets point indices into a uniform grid.
+///
+/// The grid only stores indices. Callers keep the points themselves and use
+/// the grid to narrow down which of them could possibly be near a query point.
+#[derive(Clone, Debug)]
+pub(crate) struct Grid<T = i64> {
+    size: T,
+    cells: HashMap<Cell, Vec<usize>>,
+}
+
+impl<T: Coordinate> Grid<T> {
+    /// Create an empty grid whose cells are `size` units wide on every axis.
+    ///
+    /// Queries are cheapest when `size` is the radius that will be queried.
+    pub(crate) fn new(size: T) -> Grid<T> {
+        Grid {
+            size: size.cell_size(),
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Add the point at `index` to the grid.
+    pub(crate) fn insert(&mut self, index: usize, point: &Point<T>) {
+        self.cells.entry(self.cell(point)).or_default().push(index);
+    }
+
+    /// Remove the point at `index`, which must have been inserted as `point`.
+    /// Returns false if it was not in the grid.
+    pub(crate) fn remove(&mut self, index: usize, point: &Point<T>) -> bool {
+        let cell = self.cell(point);
+        let Some(indices) = self.cells.get_mut(&cell) else {
+            return false;
+        };
+        let Some(pos) = indices.iter().position(|&i| i == index) else {
+            return false;
+        };
+        indices.swap_remove(pos);
+        if indices.is_empty() {
+            self.cells.remove(&cell);
+        }
+        true
+    }
+
+    /// Return the number of cells `for_each_candidate` looks at for `point`
+    /// and `radius`, or `u128::MAX` if there are more.
+    pub(crate) fn span(&self, point: &Point<T>, radius: T) -> u128 {
+        let (lo, hi) = self.range(point, radius);
+        span(&lo, &hi)
+    }
+
+    /// Call `f` with every indexed point that differs from `point` by at most
+    /// `radius` on every axis. Some points further away may be visited too.
+    pub(crate) fn for_each_candidate<F: FnMut(usize)>(
+        &self,
+        point: &Point<T>,
+        radius: T,
+        mut f: F,
+    ) {
+        let (lo, hi) = self.range(point, radius);
+        // When the range spans more cells than are occupied, it's cheaper to
+        // look at every occupied cell than at every cell in range.
+        if span(&lo, &hi) > self.cells.len() as u128 {
+            for (cell, indices) in &self.cells {
+                let inside =
+                    (0..cell.len()).all(|axis| lo[axis] <= cell[axis] && cell[axis] <= hi[axis]);
+                if inside {
+                    for &i in indices {
+                        f(i);
+                    }
+                }
+            }
+            return;
+        }
+
+        let mut cell = lo.clone();
+        loop {
+            if let Some(indices) = self.cells.get(&cell) {
+                for &i in indices {
+                    f(i);
+                }
+            }
+
+            let mut axis = 0;
+            while axis < cell.len() && cell[axis] == hi[axis] {
+                cell[axis] = lo[axis];
+                axis += 1;
+            }
+            if axis == cell.len() {
+                break;
+            }
+            cell[axis] += 1;
+        }
+    }
+
+    /// Return the indices of all points connected to `point` by `threshold`.
+    /// `points` must be the slice the indices were taken from.
+    pub(crate) fn within(
+        &self,
+        points: &[Point<T>],
+        point: &Point<T>,
+        threshold: Threshold<T>,
+    ) -> Vec<usize> {
+        let mut found = vec![];
+        self.for_each_candidate(point, threshold.axis_radius(), |i| {
+            if threshold.connects(point, &points[i]) {
+                found.push(i);
+            }
+        });
+        found
+    }
+
+    /// Return the first and last cells within `radius` of `point` on every
+    /// axis.
+    fn range(&self, point: &Point<T>, radius: T) -> (Cell, Cell) {
+        let zero = T::from(0);
+        let radius = if radius > zero { radius } else { zero };
+        point
+            .coords()
+            .iter()
+            .map(|&c| {
+                (
+                    c.saturating_sub(radius).cell(self.size),
+                    c.saturating_add(radius).cell(self.size),
+                )
+            })
+            .unzip()
+    }
+
+    fn cell(&self, p: &Point<T>) -> Cell {
+        p.coords().iter().map(|&c| c.cell(self.size)).collect()
+    }
+}
+
+/// Return the number of cells from `lo` to `hi`, saturating at `u128::MAX`.
+fn span(lo: &[i64], hi: &[i64]) -> u128 {
+    lo.iter().zip(hi.iter()).fold(1u128, |span, (&lo, &hi)| {
+        span.saturating_mul((hi as i128 - lo as i128 + 1) as u128)
+    })
+}
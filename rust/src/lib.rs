@@ -0,0 +1,83 @@
+/*!
+Find constellations: groups of points that are linked together by chains of
+points, each within a fixed distance of the next.
+
+Points can have any number of coordinates, which may be integers or
+floating-point numbers (see [`Coordinate`]), and distances can be measured
+with any [`Metric`]. Two points belong to the same constellation when a
+[`Threshold`] connects them directly, or when they are both connected to some
+third point in the same constellation.
+
+# Example
+
+```
+use constellation::{parse_points, Constellations, Point, Syntax, Threshold};
+
+let points: Vec<Point> = parse_points("0,0,0,0\n3,0,0,0\n9,0,0,0\n", Syntax::Strict).unwrap();
+let consts = Constellations::cluster(&points, Threshold::default());
+assert_eq!(consts.len(), 2);
+
+let points: Vec<Point<f64>> = parse_points("0,0\n0.5,0.5\n2.5,0\n", Syntax::Strict).unwrap();
+let threshold = Threshold { distance: 1.5, ..Threshold::default() };
+assert_eq!(Constellations::cluster(&points, threshold).len(), 2);
+```
+*/
+
+#![deny(missing_docs)]
+
+use std::result;
+
+pub use crate::constellation::{Clusterer, Constellation, Constellations, Threshold};
+pub use crate::coordinate::{Coordinate, Wide};
+pub use crate::diagnostic::Diagnostic;
+pub use crate::error::Error;
+pub use crate::linkage::{Dendrogram, Merge};
+pub use crate::metric::Metric;
+pub use crate::point::{Point, Syntax};
+pub use crate::query::PointSet;
+pub use crate::reader::PointReader;
+pub use crate::stats::{Stats, Summary};
+
+use crate::grid::Grid;
+use crate::unionfind::UnionFind;
+
+mod constellation;
+mod coordinate;
+mod diagnostic;
+mod error;
+mod grid;
+mod linkage;
+mod metric;
+mod point;
+mod query;
+mod reader;
+mod stats;
+mod unionfind;
+
+/// A type alias for handling errors throughout this crate.
+pub type Result<T> = result::Result<T, Error>;
+
+/// Parse the points in `input`, which is written in the given syntax.
+///
+/// Every point must have the same number of coordinates. Parsing stops at the
+/// first line that is not a valid point.
+pub fn parse_points<T: Coordinate>(input: &str, syntax: Syntax) -> Result<Vec<Point<T>>> {
+    PointReader::new(input.as_bytes(), syntax).collect()
+}
+
+/// Parse the points in `input`, carrying on past invalid lines.
+///
+/// This returns every point that could be parsed, along with an error for
+/// each line that could not, in the order the lines appear. The number of
+/// coordinates each point must have is taken from the first valid point.
+pub fn parse_points_all<T: Coordinate>(input: &str, syntax: Syntax) -> (Vec<Point<T>>, Vec<Error>) {
+    let mut points = vec![];
+    let mut errs = vec![];
+    for result in PointReader::new(input.as_bytes(), syntax) {
+        match result {
+            Ok(point) => points.push(point),
+            Err(err) => errs.push(err),
+        }
+    }
+    (points, errs)
+}
@@ -0,0 +1,208 @@
+use std::cmp::Ordering;
+
+use crate::{Coordinate, Error, Metric, Point, Result, UnionFind};
+
+/// The full single-linkage clustering of a set of points: the order in which
+/// clusters merge as the threshold grows.
+///
+/// Cutting the hierarchy at a distance gives the same constellations that a
+/// `Threshold` of that distance does. It is built from a minimum spanning
+/// tree, which takes time proportional to the square of the number of points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dendrogram<T: Coordinate = i64> {
+    num_points: usize,
+    merges: Vec<Merge<T>>,
+}
+
+/// Two clusters merging into one.
+///
+/// Clusters are numbered like in SciPy's linkage matrices: each point is
+/// cluster `i`, where `i` is its index, and the cluster formed by the merge
+/// at index `i` is cluster `n + i`, where `n` is the number of points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Merge<T: Coordinate = i64> {
+    /// The smaller of the two clusters' numbers.
+    pub left: usize,
+    /// The larger of the two clusters' numbers.
+    pub right: usize,
+    /// The distance at which the clusters merge, in raw form (see
+    /// `Metric::raw_distance`).
+    pub distance: T::Wide,
+    /// The number of points in the merged cluster.
+    pub size: usize,
+}
+
+impl<T: Coordinate> Dendrogram<T> {
+    /// Cluster `points`, which must all have the same number of coordinates,
+    /// by distances in `metric`. This fails if the distance between two
+    /// points is too large to represent.
+    pub fn new(points: &[Point<T>], metric: Metric) -> Result<Dendrogram<T>> {
+        let mut edges = spanning_tree(points, metric)?;
+        edges.sort_by(|a, b| {
+            a.0.partial_cmp(&b.0)
+                .unwrap_or(Ordering::Equal)
+                .then((a.1, a.2).cmp(&(b.1, b.2)))
+        });
+
+        let n = points.len();
+        let mut uf = UnionFind::new(n);
+        // The number of the cluster each set's root stands for.
+        let mut cluster: Vec<usize> = (0..n).collect();
+        let mut merges = Vec::with_capacity(edges.len());
+        for (distance, a, b) in edges {
+            let (ra, rb) = (uf.find(a), uf.find(b));
+            let (left, right) = (cluster[ra].min(cluster[rb]), cluster[ra].max(cluster[rb]));
+            uf.union(ra, rb);
+            let root = uf.find(a);
+            cluster[root] = n + merges.len();
+            merges.push(Merge {
+                left,
+                right,
+                distance,
+                size: uf.size(root),
+            });
+        }
+        Ok(Dendrogram {
+            num_points: n,
+            merges,
+        })
+    }
+
+    /// Return the number of points clustered.
+    pub fn num_points(&self) -> usize {
+        self.num_points
+    }
+
+    /// Return every merge, in order of increasing distance. There is one
+    /// fewer merge than there are points, unless there are none.
+    pub fn merges(&self) -> &[Merge<T>] {
+        &self.merges
+    }
+
+    /// Return the number of clusters left when every pair of clusters at
+    /// most `raw_limit` apart in raw form has been merged. This is the
+    /// number of constellations a `Threshold` with that limit finds.
+    pub fn count_at(&self, raw_limit: T::Wide) -> usize {
+        let merged = self.merges.partition_point(|m| m.distance <= raw_limit);
+        self.num_points - merged
+    }
+}
+
+/// Return the edges of a minimum spanning tree over `points`, as raw
+/// distances and the indices of the two points, smaller first.
+///
+/// This is Prim's algorithm on the complete graph, which needs no index and
+/// only linear space.
+fn spanning_tree<T: Coordinate>(
+    points: &[Point<T>],
+    metric: Metric,
+) -> Result<Vec<(T::Wide, usize, usize)>> {
+    let n = points.len();
+    let mut edges = Vec::with_capacity(n.saturating_sub(1));
+    if n == 0 {
+        return Ok(edges);
+    }
+    let mut in_tree = vec![false; n];
+    // The nearest point in the tree to each point outside it, and how far.
+    let mut nearest: Vec<Option<(T::Wide, usize)>> = vec![None; n];
+    let mut current = 0;
+    in_tree[current] = true;
+    for _ in 1..n {
+        let mut next: Option<(T::Wide, usize)> = None;
+        for j in 0..n {
+            if in_tree[j] {
+                continue;
+            }
+            let distance = metric
+                .raw_distance(&points[current], &points[j])
+                .ok_or_else(|| {
+                    Error::Overflow(format!(
+                        "the distance between {} and {}",
+                        points[current], points[j]
+                    ))
+                })?;
+            if nearest[j].is_none_or(|(best, _)| distance < best) {
+                nearest[j] = Some((distance, current));
+            }
+            let best = nearest[j].unwrap().0;
+            if next.is_none_or(|(closest, _)| best < closest) {
+                next = Some((best, j));
+            }
+        }
+        let (distance, j) = next.unwrap();
+        let from = nearest[j].unwrap().1;
+        edges.push((distance, from.min(j), from.max(j)));
+        in_tree[j] = true;
+        current = j;
+    }
+    Ok(edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Constellations, Threshold};
+
+    fn line(xs: &[i64]) -> Vec<Point> {
+        xs.iter().map(|&x| Point::new(vec![x])).collect()
+    }
+
+    #[test]
+    fn merge_table() {
+        let dendrogram = Dendrogram::new(&line(&[0, 10, 1, 4]), Metric::Manhattan).unwrap();
+        assert_eq!(dendrogram.num_points(), 4);
+        let merge = |left, right, distance, size| Merge {
+            left,
+            right,
+            distance,
+            size,
+        };
+        assert_eq!(
+            dendrogram.merges(),
+            [merge(0, 2, 1, 2), merge(3, 4, 3, 3), merge(1, 5, 6, 4)]
+        );
+        assert_eq!(dendrogram.count_at(0), 4);
+        assert_eq!(dendrogram.count_at(3), 2);
+        assert_eq!(dendrogram.count_at(6), 1);
+
+        let empty = Dendrogram::<i64>::new(&[], Metric::Manhattan).unwrap();
+        assert_eq!((empty.num_points(), empty.merges().len()), (0, 0));
+        assert_eq!(empty.count_at(5), 0);
+    }
+
+    #[test]
+    fn count_at_matches_cluster() {
+        // A xorshift generator, so that the test is repeatable.
+        let mut state: u64 = 0x5851f42d4c957f2d;
+        let mut coord = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 41) as i64 - 20
+        };
+        let points: Vec<Point> = (0..150)
+            .map(|_| Point::new(vec![coord(), coord(), coord()]))
+            .collect();
+        for metric in [
+            Metric::Manhattan,
+            Metric::Chebyshev,
+            Metric::SquaredEuclidean,
+            Metric::Minkowski(3),
+        ] {
+            let dendrogram = Dendrogram::new(&points, metric).unwrap();
+            for distance in [0, 1, 2, 3, 5, 8, 13] {
+                let threshold = Threshold {
+                    distance,
+                    metric,
+                    ..Threshold::default()
+                };
+                assert_eq!(
+                    dendrogram.count_at(metric.raw_limit(distance).unwrap()),
+                    Constellations::cluster(&points, threshold).len(),
+                    "{:?}",
+                    threshold
+                );
+            }
+        }
+    }
+}
@@ -1,133 +1,798 @@
+use std::env;
 use std::error::Error;
-use std::i32;
-use std::io::{self, Read, Write};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 use std::result;
-use std::str::{self, FromStr};
 
-macro_rules! err {
-    ($($tt:tt)*) => { Err(Box::<Error>::from(format!($($tt)*))) }
+use constellation::{
+    Clusterer, Constellations, Coordinate, Diagnostic, Metric, Point, PointReader, Syntax,
+    Threshold,
+};
+
+use crate::report::{Format, Reporter, Search};
+
+mod report;
+
+type Result<T> = result::Result<T, CliError>;
+
+/// An error that stops the command line tool.
+///
+/// Where an error wraps another, its message already includes the wrapped
+/// error's, so `source` skips ahead to what caused the wrapped error.
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line was invalid.
+    Usage(String),
+    /// The points read could not be used as asked, such as when query points
+    /// have a different number of coordinates than the input.
+    Input(String),
+    /// A file could not be opened or listed.
+    File { path: PathBuf, err: io::Error },
+    /// Reading the input named `label` failed.
+    Read {
+        label: String,
+        err: constellation::Error,
+    },
+    /// Clustering or reporting failed.
+    Library(constellation::Error),
+    /// Writing the results failed.
+    Io(io::Error),
+    /// This many invalid lines were found, and each has been reported.
+    Aborted(usize),
 }
 
-type Result<T> = result::Result<T, Box<Error>>;
-
-fn main() -> Result<()> {
-    let mut input = String::new();
-    io::stdin().read_to_string(&mut input)?;
+impl Error for CliError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            CliError::File { ref err, .. } | CliError::Io(ref err) => err.source(),
+            CliError::Read { ref err, .. } | CliError::Library(ref err) => err.source(),
+            CliError::Usage(_) | CliError::Input(_) | CliError::Aborted(_) => None,
+        }
+    }
+}
 
-    let mut points: Vec<Point> = vec![];
-    for line in input.lines() {
-        let point = line
-            .parse()
-            .or_else(|err| err!("failed to parse '{:?}': {}", line, err))?;
-        points.push(point);
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CliError::Usage(ref message) | CliError::Input(ref message) => {
+                write!(f, "{}", message)
+            }
+            CliError::File { ref path, ref err } => write!(f, "{}: {}", path.display(), err),
+            CliError::Read { ref label, ref err } => write!(f, "{}: {}", label, err),
+            CliError::Library(ref err) => write!(f, "{}", err),
+            CliError::Io(ref err) => write!(f, "{}", err),
+            CliError::Aborted(1) => write!(f, "aborting due to previous error"),
+            CliError::Aborted(n) => write!(f, "aborting due to {} previous errors", n),
+        }
     }
+}
 
-    part1(&points)?;
-    Ok(())
+impl From<constellation::Error> for CliError {
+    fn from(err: constellation::Error) -> CliError {
+        CliError::Library(err)
+    }
 }
 
-fn part1(points: &[Point]) -> Result<()> {
-    let mut consts = Constellations::shatter_all(points);
-    while consts.step() {}
-    writeln!(io::stdout(), "constellations: {}", consts.groups.len())?;
-    Ok(())
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> CliError {
+        CliError::Io(err)
+    }
 }
 
-#[derive(Clone, Debug)]
-struct Constellations {
-    groups: Vec<Constellation>,
+const USAGE: &str = "\
+Find constellations of points that are linked by chains of nearby points.
+
+Usage: rust [COMMAND] [OPTIONS] [FILE...]
+
+Points are read one per line from each FILE, or from stdin if no FILE is
+given or FILE is '-'. A FILE may use '*' and '?' wildcards in its file name.
+Each FILE is clustered on its own unless --merge is given.
+
+Commands:
+    count       Print the number of constellations (the default)
+    list        Print every constellation and the points in it
+    label       Print every point, in input order, with its constellation
+    stats       Print sizes, bounding boxes, centroids and diameters
+    query       Print the points near each point given by --point or
+                --queries: those connected to it, or those found by
+                --radius or --nearest
+    lookup      Print the constellations each point given by --point or
+                --queries would join, and whether it would merge them
+    explain     Print the shortest chain of connected points linking the
+                two points given by --point, if they are in the same
+                constellation
+    linkage     Print the single-linkage merge table: the order in which
+                constellations merge as the threshold grows
+    validate    Check that the input is well formed, reporting every error
+
+Options:
+    -t, --threshold N   Connect points at most N apart [default: 3]
+        --strict        Connect points strictly less than the threshold apart
+        --tolerance X   Treat distances within X of the threshold as equal
+                        to it [default: 0]
+    -m, --metric NAME   One of manhattan, chebyshev, squared-euclidean or
+                        minkowski:P [default: manhattan]
+        --lenient       Allow comments, blank lines and other separators
+        --float         Read coordinates as floating-point numbers
+        --all-errors    Report every invalid line instead of stopping
+        --merge         Cluster all inputs together
+    -j, --threads N     Cluster using N threads, or one per CPU if N is 0
+                        [default: 1]
+    -p, --point POINT   A point to query (may be repeated)
+    -q, --queries FILE  Read points to query from FILE, or from stdin if FILE
+                        is '-'
+    -r, --radius R      Find the points at most R from each query point
+    -k, --nearest K     Find the K points nearest each query point
+    -f, --format FMT    One of text, json, csv or ndjson [default: text].
+                        Only count, list, label, stats and linkage support
+                        formats other than text, and lookup supports json
+                        and ndjson.
+    -h, --help          Print this help and exit
+    -V, --version       Print the version and exit
+";
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        let mut source = err.source();
+        while let Some(err) = source {
+            eprintln!("  caused by: {}", err);
+            source = err.source();
+        }
+        process::exit(1);
+    }
 }
 
-impl Constellations {
-    fn shatter_all(points: &[Point]) -> Constellations {
-        let mut groups = vec![];
-        for &p in points {
-            groups.push(Constellation { points: vec![p] });
+fn run() -> Result<()> {
+    let args = match Args::parse(env::args().skip(1))? {
+        Parsed::Run(args) => args,
+        Parsed::Help => {
+            write!(io::stdout(), "{}", USAGE)?;
+            return Ok(());
         }
-        Constellations { groups }
+        Parsed::Version => {
+            writeln!(io::stdout(), "rust {}", env!("CARGO_PKG_VERSION"))?;
+            return Ok(());
+        }
+    };
+    let inputs = args.inputs()?;
+    if args.float {
+        run_with::<f64>(&args, &inputs)
+    } else {
+        run_with::<i64>(&args, &inputs)
     }
+}
 
-    fn step(&mut self) -> bool {
-        for i in 0..self.groups.len() {
-            for j in i + 1..self.groups.len() {
-                if self.groups[i].is_connected(&self.groups[j]) {
-                    self.merge(i, j);
-                    return true;
-                }
+/// Run the command in `args` on `inputs`, reading coordinates as `T`.
+fn run_with<T: Coordinate>(args: &Args, inputs: &[Input]) -> Result<()> {
+    match args.command {
+        Command::Validate => return validate::<T>(args, inputs),
+        Command::Linkage => return linkage::<T>(args, inputs),
+        _ => {}
+    }
+
+    let threshold: Threshold<T> = args.threshold()?;
+    let labelled = !args.merge && inputs.len() > 1;
+    let mut queries = args.queries()?;
+    if let Some(ref path) = args.query_file {
+        let input = if path == "-" {
+            Input::Stdin
+        } else {
+            Input::Path(path.into())
+        };
+        abort_on_errors(read_points(&input, args, &mut None, |p| queries.push(p))?)?;
+    }
+    let mut reporter = Reporter::new(args, queries, args.search()?, labelled);
+    let mut errors = 0;
+    if args.merge {
+        let mut clusterer = Collector::new(threshold, args.threads);
+        for input in inputs {
+            errors += cluster_into(input, args, &mut clusterer)?;
+            if errors > 0 && !args.all_errors {
+                break;
+            }
+        }
+        if errors == 0 {
+            reporter.report("<merged>", &clusterer.finish())?;
+        }
+    } else {
+        for input in inputs {
+            let mut clusterer = Collector::new(threshold, args.threads);
+            let file_errors = cluster_into(input, args, &mut clusterer)?;
+            errors += file_errors;
+            if file_errors == 0 {
+                reporter.report(&input.to_string(), &clusterer.finish())?;
+            } else if !args.all_errors {
+                break;
             }
         }
-        false
     }
+    reporter.finish()?;
+    abort_on_errors(errors)
+}
 
-    fn merge(&mut self, i1: usize, i2: usize) {
-        let g2 = self.groups.swap_remove(i2);
-        self.groups[i1].join(&g2);
+fn abort_on_errors(errors: usize) -> Result<()> {
+    match errors {
+        0 => Ok(()),
+        n => Err(CliError::Aborted(n)),
     }
 }
 
+/// Check every input for errors without clustering.
+fn validate<T: Coordinate>(args: &Args, inputs: &[Input]) -> Result<()> {
+    let mut errors = 0;
+    let mut dimension = None;
+    for input in inputs {
+        if !args.merge {
+            dimension = None;
+        }
+        let mut points = 0;
+        let file_errors = read_points(input, args, &mut dimension, |_: Point<T>| points += 1)?;
+        errors += file_errors;
+        if file_errors == 0 {
+            writeln!(io::stdout(), "{}: {} points OK", input, points)?;
+        } else if !args.all_errors {
+            break;
+        }
+    }
+    abort_on_errors(errors)
+}
+
+/// Print the single-linkage merge table of every input, without clustering
+/// them at any one threshold.
+fn linkage<T: Coordinate>(args: &Args, inputs: &[Input]) -> Result<()> {
+    let labelled = !args.merge && inputs.len() > 1;
+    let mut reporter = Reporter::new(args, vec![], Search::Connected, labelled);
+    let mut errors = 0;
+    let mut points: Vec<Point<T>> = vec![];
+    let mut dimension = None;
+    for input in inputs {
+        if !args.merge {
+            points.clear();
+            dimension = None;
+        }
+        let file_errors = read_points(input, args, &mut dimension, |p| points.push(p))?;
+        errors += file_errors;
+        if file_errors == 0 && !args.merge {
+            reporter.report_linkage(&input.to_string(), &points)?;
+        } else if file_errors > 0 && !args.all_errors {
+            break;
+        }
+    }
+    if args.merge && errors == 0 {
+        reporter.report_linkage("<merged>", &points)?;
+    }
+    reporter.finish()?;
+    abort_on_errors(errors)
+}
+
+/// Gathers points to cluster. With one thread, points are clustered as they
+/// are read. With more, they are all clustered in parallel at the end.
 #[derive(Clone, Debug)]
-struct Constellation {
-    points: Vec<Point>,
+enum Collector<T> {
+    Online(Clusterer<T>),
+    Parallel {
+        threshold: Threshold<T>,
+        threads: usize,
+        points: Vec<Point<T>>,
+    },
 }
 
-impl Constellation {
-    fn join(&mut self, other: &Constellation) {
-        self.points.extend(other.points.iter().cloned());
+impl<T: Coordinate> Collector<T> {
+    fn new(threshold: Threshold<T>, threads: usize) -> Collector<T> {
+        match threads {
+            1 => Collector::Online(Clusterer::new(threshold)),
+            threads => Collector::Parallel {
+                threshold,
+                threads,
+                points: vec![],
+            },
+        }
     }
 
-    fn is_connected(&self, other: &Constellation) -> bool {
-        for p in other.points.iter() {
-            if self.is_point_connected(p) {
-                return true;
+    fn push(&mut self, point: Point<T>) {
+        match *self {
+            Collector::Online(ref mut clusterer) => {
+                clusterer.push(point);
             }
+            Collector::Parallel { ref mut points, .. } => points.push(point),
         }
-        false
     }
 
-    fn is_point_connected(&self, point: &Point) -> bool {
-        for p in self.points.iter() {
-            if point.distance(p) <= 3 {
-                return true;
+    fn dimension(&self) -> Option<usize> {
+        match *self {
+            Collector::Online(ref clusterer) => clusterer.dimension(),
+            Collector::Parallel { ref points, .. } => points.first().map(|p| p.dimension()),
+        }
+    }
+
+    fn finish(self) -> Constellations<T> {
+        match self {
+            Collector::Online(clusterer) => clusterer.finish(),
+            Collector::Parallel {
+                threshold,
+                threads,
+                points,
+            } => Constellations::cluster_parallel(&points, threshold, threads),
+        }
+    }
+}
+
+/// Feed every point in `input` to `clusterer`, printing a diagnostic for each
+/// invalid line. Returns the number of invalid lines found.
+fn cluster_into<T: Coordinate>(
+    input: &Input,
+    args: &Args,
+    clusterer: &mut Collector<T>,
+) -> Result<usize> {
+    let mut dimension = clusterer.dimension();
+    read_points(input, args, &mut dimension, |point| clusterer.push(point))
+}
+
+/// Call `f` with every point in `input`, printing a diagnostic for each
+/// invalid line. Returns the number of invalid lines found.
+///
+/// If `dimension` is set, every point must have that many coordinates.
+/// Otherwise, it is set from the first valid point.
+fn read_points<T: Coordinate, F: FnMut(Point<T>)>(
+    input: &Input,
+    args: &Args,
+    dimension: &mut Option<usize>,
+    mut f: F,
+) -> Result<usize> {
+    let mut reader = PointReader::new(input.open()?, args.syntax);
+    if let Some(dimension) = *dimension {
+        reader.set_dimension(dimension);
+    }
+    let label = input.to_string();
+    let mut errors = 0;
+    while let Some(result) = reader.next() {
+        match result {
+            Ok(point) => f(point),
+            Err(err @ constellation::Error::Io(_)) => {
+                return Err(CliError::Read { label, err });
+            }
+            Err(err) => {
+                errors += 1;
+                eprintln!("{}", Diagnostic::new(&err, &label, reader.line()));
+                if !args.all_errors {
+                    break;
+                }
             }
         }
-        false
     }
+    *dimension = reader.dimension();
+    Ok(errors)
 }
 
-#[derive(Clone, Copy, Debug)]
-struct Point {
-    x: i32,
-    y: i32,
-    z: i32,
-    t: i32,
+/// A source of points named on the command line.
+#[derive(Clone, Debug)]
+enum Input {
+    Stdin,
+    Path(PathBuf),
 }
 
-impl Point {
-    fn distance(&self, other: &Point) -> i32 {
-        (self.x - other.x).abs()
-            + (self.y - other.y).abs()
-            + (self.z - other.z).abs()
-            + (self.t - other.t).abs()
+impl Input {
+    fn open(&self) -> Result<Box<dyn BufRead>> {
+        match *self {
+            Input::Stdin => Ok(Box::new(io::stdin().lock())),
+            Input::Path(ref path) => match File::open(path) {
+                Ok(file) => Ok(Box::new(BufReader::new(file))),
+                Err(err) => Err(CliError::File {
+                    path: path.clone(),
+                    err,
+                }),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Input::Stdin => write!(f, "<stdin>"),
+            Input::Path(ref path) => write!(f, "{}", path.display()),
+        }
     }
 }
 
-impl FromStr for Point {
-    type Err = Box<Error>;
+/// Parse a single coordinate given on the command line as the value of
+/// `what`.
+fn parse_coordinate<T: Coordinate>(what: &str, value: &str) -> Result<T> {
+    T::parse_field(1, value).map_err(|err| {
+        let reason = match err.source() {
+            Some(source) => source.to_string(),
+            None => "not a finite number".to_string(),
+        };
+        CliError::Usage(format!("invalid {} '{}': {}", what, value, reason))
+    })
+}
+
+/// Expand a path whose final component may contain `*` or `?` wildcards into
+/// the sorted list of paths it matches. Paths without wildcards are returned
+/// as is, whether or not they exist.
+fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.contains(['*', '?']) => name,
+        _ => return Ok(vec![path.to_path_buf()]),
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if dir.to_string_lossy().contains(['*', '?']) {
+        return Err(CliError::Usage(format!(
+            "wildcards are only supported in file names: {}",
+            pattern
+        )));
+    }
 
-    fn from_str(s: &str) -> Result<Point> {
-        let parts: Vec<&str> = s.trim().split(",").collect();
-        if parts.len() != 4 {
-            return err!("unrecognized point '{:?}'", s);
+    let mut paths = vec![];
+    let entries = fs::read_dir(dir).map_err(|err| CliError::File {
+        path: dir.to_path_buf(),
+        err,
+    })?;
+    for entry in entries {
+        let entry = entry?;
+        let matches = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|candidate| glob_match(name, candidate));
+        if matches && entry.file_type()?.is_file() {
+            paths.push(path.with_file_name(entry.file_name()));
+        }
+    }
+    if paths.is_empty() {
+        return Err(CliError::Usage(format!("no files match {}", pattern)));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Return true if `name` matches `pattern`, where `*` matches any run of
+/// characters and `?` matches any single character.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where to resume if the most recent `*` needs to match more characters.
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            backtrack = Some((star, matched + 1));
+            p = star + 1;
+            n = matched + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Command {
+    Count,
+    List,
+    Label,
+    Stats,
+    Query,
+    Lookup,
+    Explain,
+    Linkage,
+    Validate,
+}
+
+impl Command {
+    fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "count" => Some(Command::Count),
+            "list" => Some(Command::List),
+            "label" => Some(Command::Label),
+            "stats" => Some(Command::Stats),
+            "query" => Some(Command::Query),
+            "lookup" => Some(Command::Lookup),
+            "explain" => Some(Command::Explain),
+            "linkage" => Some(Command::Linkage),
+            "validate" => Some(Command::Validate),
+            _ => None,
+        }
+    }
+}
+
+/// What the command line asked for.
+#[derive(Clone, Debug)]
+enum Parsed {
+    Run(Args),
+    Help,
+    Version,
+}
+
+#[derive(Clone, Debug)]
+struct Args {
+    command: Command,
+    format: Format,
+    /// The threshold's distance and tolerance, if given. They are parsed
+    /// once it's known whether coordinates are integers or floats.
+    distance: Option<String>,
+    tolerance: Option<String>,
+    strict: bool,
+    metric: Metric,
+    float: bool,
+    all_errors: bool,
+    syntax: Syntax,
+    merge: bool,
+    threads: usize,
+    /// The points given with --point, parsed like the threshold.
+    points: Vec<String>,
+    query_file: Option<String>,
+    radius: Option<String>,
+    nearest: Option<usize>,
+    paths: Vec<String>,
+}
+
+impl Args {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Parsed> {
+        let mut parsed = Args {
+            command: Command::Count,
+            format: Format::Text,
+            distance: None,
+            tolerance: None,
+            strict: false,
+            metric: Metric::Manhattan,
+            float: false,
+            all_errors: false,
+            syntax: Syntax::Strict,
+            merge: false,
+            threads: 1,
+            points: vec![],
+            query_file: None,
+            radius: None,
+            nearest: None,
+            paths: vec![],
+        };
+        let mut saw_command = false;
+        while let Some(arg) = args.next() {
+            if arg == "-" || !arg.starts_with('-') {
+                match Command::from_name(&arg) {
+                    Some(command) if !saw_command => {
+                        parsed.command = command;
+                    }
+                    _ => parsed.paths.push(arg),
+                }
+                saw_command = true;
+                continue;
+            }
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = || match inline.clone().or_else(|| args.next()) {
+                Some(value) => Ok(value),
+                None => Err(CliError::Usage(format!("{} requires a value", flag))),
+            };
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(Parsed::Help),
+                "-V" | "--version" => return Ok(Parsed::Version),
+                "-t" | "--threshold" => parsed.distance = Some(value()?),
+                "--tolerance" => parsed.tolerance = Some(value()?),
+                "--strict" => parsed.strict = true,
+                "-m" | "--metric" => parsed.metric = value()?.parse()?,
+                "--float" => parsed.float = true,
+                "--all-errors" => parsed.all_errors = true,
+                "--lenient" => parsed.syntax = Syntax::Lenient,
+                "--merge" => parsed.merge = true,
+                "-j" | "--threads" => {
+                    let value = value()?;
+                    parsed.threads = value.parse().map_err(|err| {
+                        CliError::Usage(format!("invalid thread count '{}': {}", value, err))
+                    })?;
+                }
+                "-p" | "--point" => parsed.points.push(value()?),
+                "-q" | "--queries" => parsed.query_file = Some(value()?),
+                "-r" | "--radius" => parsed.radius = Some(value()?),
+                "-k" | "--nearest" => {
+                    let value = value()?;
+                    parsed.nearest = Some(value.parse().map_err(|err| {
+                        CliError::Usage(format!("invalid count '{}': {}", value, err))
+                    })?);
+                }
+                "-f" | "--format" => parsed.format = value()?.parse()?,
+                _ => {
+                    return Err(CliError::Usage(format!(
+                        "unrecognized argument '{}' (see --help)",
+                        flag
+                    )))
+                }
+            }
+        }
+
+        let querying = matches!(parsed.command, Command::Query | Command::Lookup);
+        if !parsed.points.is_empty() && !querying && parsed.command != Command::Explain {
+            return Err(CliError::Usage(
+                "--point can only be used with query, lookup or explain".to_string(),
+            ));
+        }
+        if parsed.query_file.is_some() && !querying {
+            return Err(CliError::Usage(
+                "--queries can only be used with query or lookup".to_string(),
+            ));
+        }
+        for (flag, given) in [
+            ("--radius", parsed.radius.is_some()),
+            ("--nearest", parsed.nearest.is_some()),
+        ] {
+            if given && parsed.command != Command::Query {
+                return Err(CliError::Usage(format!(
+                    "{} can only be used with query",
+                    flag
+                )));
+            }
+        }
+        // The merge table covers every threshold at once.
+        for (flag, given) in [
+            ("--threshold", parsed.distance.is_some()),
+            ("--strict", parsed.strict),
+            ("--tolerance", parsed.tolerance.is_some()),
+        ] {
+            if given && parsed.command == Command::Linkage {
+                return Err(CliError::Usage(format!(
+                    "{} can't be used with linkage",
+                    flag
+                )));
+            }
+        }
+        if parsed.command == Command::Explain && parsed.points.len() != 2 {
+            return Err(CliError::Usage(
+                "explain requires exactly two --point".to_string(),
+            ));
+        }
+        if querying {
+            if parsed.points.is_empty() && parsed.query_file.is_none() {
+                let name = if parsed.command == Command::Query {
+                    "query"
+                } else {
+                    "lookup"
+                };
+                return Err(CliError::Usage(format!(
+                    "{} requires at least one --point, or --queries",
+                    name
+                )));
+            }
+            if parsed.radius.is_some() && parsed.nearest.is_some() {
+                return Err(CliError::Usage(
+                    "--radius and --nearest can't be used together".to_string(),
+                ));
+            }
+            let stdin = parsed.paths.is_empty() || parsed.paths.iter().any(|p| p == "-");
+            if stdin && parsed.query_file.as_deref() == Some("-") {
+                return Err(CliError::Usage(
+                    "the input and --queries can't both be read from stdin".to_string(),
+                ));
+            }
+        }
+        // Catch a bad threshold, radius or query point before reading any
+        // input.
+        if parsed.float {
+            parsed.threshold::<f64>()?;
+            parsed.search::<f64>()?;
+            parsed.queries::<f64>()?;
+        } else {
+            parsed.threshold::<i64>()?;
+            parsed.search::<i64>()?;
+            parsed.queries::<i64>()?;
+        }
+        if parsed.command == Command::Validate {
+            parsed.all_errors = true;
+        }
+        let formatted = match parsed.command {
+            Command::Count | Command::List | Command::Label | Command::Stats | Command::Linkage => {
+                true
+            }
+            Command::Lookup => parsed.format != Format::Csv,
+            Command::Query | Command::Explain | Command::Validate => false,
+        };
+        if parsed.format != Format::Text && !formatted {
+            return Err(CliError::Usage(
+                "only count, list, label, stats and linkage support --format, and \
+                 lookup supports json and ndjson"
+                    .to_string(),
+            ));
+        }
+        Ok(Parsed::Run(parsed))
+    }
+
+    /// Return the threshold to connect points with `T` coordinates by.
+    fn threshold<T: Coordinate>(&self) -> Result<Threshold<T>> {
+        let distance = self.distance.as_deref().unwrap_or("3");
+        let tolerance = self.tolerance.as_deref().unwrap_or("0");
+        let threshold = Threshold {
+            distance: parse_coordinate("threshold", distance)?,
+            strict: self.strict,
+            metric: self.metric,
+            tolerance: parse_coordinate("tolerance", tolerance)?,
+        };
+        if threshold.tolerance < T::from(0) {
+            return Err(CliError::Usage(format!(
+                "invalid tolerance '{}': must not be negative",
+                tolerance
+            )));
+        }
+        threshold.check()?;
+        Ok(threshold)
+    }
+
+    /// Return what the query command should look for around each point.
+    fn search<T: Coordinate>(&self) -> Result<Search<T>> {
+        if let Some(k) = self.nearest {
+            return Ok(Search::Nearest(k));
+        }
+        let Some(ref value) = self.radius else {
+            return Ok(Search::Connected);
+        };
+        let radius = parse_coordinate("radius", value)?;
+        if radius < T::from(0) {
+            return Err(CliError::Usage(format!(
+                "invalid radius '{}': must not be negative",
+                value
+            )));
+        }
+        Ok(Search::Radius(radius))
+    }
+
+    /// Return the points given with --point.
+    fn queries<T: Coordinate>(&self) -> Result<Vec<Point<T>>> {
+        let mut queries = vec![];
+        for point in &self.points {
+            match self.syntax.parse_line(point) {
+                Ok(Some(p)) => queries.push(p),
+                Ok(None) => {
+                    return Err(CliError::Usage(format!(
+                        "invalid point '{}': no coordinates",
+                        point
+                    )))
+                }
+                Err(err) => {
+                    return Err(CliError::Usage(format!(
+                        "invalid point '{}': {}",
+                        point, err
+                    )))
+                }
+            }
+        }
+        Ok(queries)
+    }
+
+    /// Return the inputs to read, in order. With no paths given, this is just
+    /// stdin.
+    fn inputs(&self) -> Result<Vec<Input>> {
+        if self.paths.is_empty() {
+            return Ok(vec![Input::Stdin]);
+        }
+        let mut inputs = vec![];
+        for path in &self.paths {
+            if path == "-" {
+                inputs.push(Input::Stdin);
+                continue;
+            }
+            for path in expand_glob(path)? {
+                inputs.push(Input::Path(path));
+            }
         }
-        Ok(Point {
-            x: parts[0].parse()?,
-            y: parts[1].parse()?,
-            z: parts[2].parse()?,
-            t: parts[3].parse()?,
-        })
+        Ok(inputs)
     }
 }
 
+#[allow(dead_code)]
 fn lolzers() {
     println!(
         "
@@ -0,0 +1,183 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Coordinate, Error, Point, Result, Wide};
+
+/// A way of measuring the distance between two points.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Metric {
+    /// The sum of the absolute differences on each axis (L1).
+    Manhattan,
+    /// The largest absolute difference on any axis (L∞).
+    Chebyshev,
+    /// The sum of the squared differences on each axis.
+    SquaredEuclidean,
+    /// The `p`-th root of the sum of the `p`-th powers of the absolute
+    /// differences on each axis (Lp).
+    Minkowski(u32),
+}
+
+impl Metric {
+    /// Return the distance between `a` and `b` in this metric's raw form, or
+    /// `None` if it is too large to represent.
+    ///
+    /// Raw distances are the actual distance for every metric except
+    /// Minkowski, where the final `p`-th root is skipped so that distances stay
+    /// exact. Raw distances order points the same way actual distances do, so
+    /// they can be compared against `raw_limit`.
+    pub fn raw_distance<T: Coordinate>(&self, a: &Point<T>, b: &Point<T>) -> Option<T::Wide> {
+        let mut diffs = a
+            .coords()
+            .iter()
+            .zip(b.coords().iter())
+            .map(|(&x, &y)| x.widen().checked_sub(y.widen()).map(Wide::abs));
+        let zero = T::Wide::ZERO;
+        match *self {
+            Metric::Manhattan => diffs.try_fold(zero, |sum, d| sum.checked_add(d?)),
+            Metric::Chebyshev => {
+                diffs.try_fold(zero, |max, d| d.map(|d| if d > max { d } else { max }))
+            }
+            Metric::SquaredEuclidean => {
+                diffs.try_fold(zero, |sum, d| sum.checked_add(d?.checked_mul(d?)?))
+            }
+            Metric::Minkowski(p) => {
+                diffs.try_fold(zero, |sum, d| sum.checked_add(d?.checked_pow(p)?))
+            }
+        }
+    }
+
+    /// Convert `distance` into the same raw form used by `raw_distance`, or
+    /// `None` if it is too large to represent.
+    pub fn raw_limit<T: Coordinate>(&self, distance: T) -> Option<T::Wide> {
+        self.raw_power(distance.widen())
+    }
+
+    /// Like `raw_limit`, for a distance that has already been widened.
+    pub(crate) fn raw_power<W: Wide>(&self, distance: W) -> Option<W> {
+        // No raw distance is negative, so a negative limit stays negative.
+        if distance < W::ZERO {
+            return Some(distance);
+        }
+        match *self {
+            Metric::Minkowski(p) => distance.checked_pow(p),
+            _ => Some(distance),
+        }
+    }
+
+    /// Return the largest difference on a single axis that two points can
+    /// have while still being at most `distance` apart.
+    pub fn axis_radius<T: Coordinate>(&self, distance: T) -> T {
+        match *self {
+            Metric::SquaredEuclidean => distance.sqrt_floor(),
+            _ => distance,
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Metric::Manhattan => write!(f, "manhattan"),
+            Metric::Chebyshev => write!(f, "chebyshev"),
+            Metric::SquaredEuclidean => write!(f, "squared-euclidean"),
+            Metric::Minkowski(p) => write!(f, "minkowski:{}", p),
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Metric> {
+        let (name, param) = match s.split_once(':') {
+            Some((name, param)) => (name, Some(param)),
+            None => (s, None),
+        };
+        match (name, param) {
+            ("manhattan", None) => Ok(Metric::Manhattan),
+            ("chebyshev", None) => Ok(Metric::Chebyshev),
+            ("squared-euclidean", None) => Ok(Metric::SquaredEuclidean),
+            ("minkowski", Some(p)) => match p.parse() {
+                Ok(p) if p >= 1 => Ok(Metric::Minkowski(p)),
+                _ => Err(Error::UnknownMetric(s.to_string())),
+            },
+            _ => Err(Error::UnknownMetric(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Threshold;
+
+    const METRICS: [Metric; 4] = [
+        Metric::Manhattan,
+        Metric::Chebyshev,
+        Metric::SquaredEuclidean,
+        Metric::Minkowski(3),
+    ];
+
+    #[test]
+    fn raw_distance_overflow() {
+        let (min, max) = (Point::new(vec![i64::MIN]), Point::new(vec![i64::MAX]));
+        let width = i64::MAX as i128 - i64::MIN as i128;
+        assert_eq!(Metric::Manhattan.raw_distance(&min, &max), Some(width));
+        assert_eq!(Metric::Chebyshev.raw_distance(&min, &max), Some(width));
+        assert_eq!(Metric::SquaredEuclidean.raw_distance(&min, &max), None);
+        assert_eq!(Metric::Minkowski(3).raw_distance(&min, &max), None);
+        assert_eq!(Metric::Minkowski(1).raw_distance(&min, &max), Some(width));
+    }
+
+    #[test]
+    fn connects_never_wraps() {
+        let (min, max) = (Point::new(vec![i64::MIN, 0]), Point::new(vec![i64::MAX, 0]));
+        let near = Point::new(vec![i64::MAX - 1, 0]);
+        for metric in METRICS {
+            for distance in [1, i64::MAX] {
+                let threshold = Threshold {
+                    distance,
+                    metric,
+                    ..Threshold::default()
+                };
+                if threshold.check().is_err() {
+                    continue;
+                }
+                assert!(!threshold.connects(&min, &max), "{:?}", threshold);
+                assert!(!threshold.connects(&max, &min), "{:?}", threshold);
+                assert!(threshold.connects(&max, &near), "{:?}", threshold);
+            }
+        }
+    }
+
+    #[test]
+    fn check_rejects_huge_minkowski_thresholds() {
+        let threshold = |distance, metric| Threshold {
+            distance,
+            metric,
+            ..Threshold::default()
+        };
+        for metric in METRICS {
+            assert!(threshold(1000, metric).check().is_ok());
+        }
+        assert!(threshold(i64::MAX, Metric::SquaredEuclidean)
+            .check()
+            .is_ok());
+        assert!(threshold(i64::MAX, Metric::Minkowski(2)).check().is_ok());
+        assert!(matches!(
+            threshold(i64::MAX, Metric::Minkowski(3)).check(),
+            Err(Error::Overflow(_))
+        ));
+        assert!(matches!(
+            threshold(10_000_000_000_000, Metric::Minkowski(3)).check(),
+            Err(Error::Overflow(_))
+        ));
+        assert!(threshold(1_000_000_000_000, Metric::Minkowski(3))
+            .check()
+            .is_ok());
+        // The tolerance counts towards the limit.
+        let mut tolerant = threshold(1_000_000_000_000, Metric::Minkowski(3));
+        tolerant.tolerance = 9_000_000_000_000;
+        assert!(matches!(tolerant.check(), Err(Error::Overflow(_))));
+    }
+}
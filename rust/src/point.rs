@@ -0,0 +1,283 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::coordinate::Wide;
+use crate::{Coordinate, Error, Result};
+
+/// A point with any number of coordinates, which are `i64` unless stated
+/// otherwise.
+///
+/// Points are ordered lexicographically by their coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Point<T = i64> {
+    coords: Vec<T>,
+}
+
+impl<T: Coordinate> Point<T> {
+    /// Create a point from its coordinates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a coordinate is NaN or infinite.
+    pub fn new(coords: Vec<T>) -> Point<T> {
+        let coords = coords
+            .into_iter()
+            .map(|c| c.canonical().expect("coordinates must be finite"))
+            .collect();
+        Point { coords }
+    }
+
+    /// Return this point's coordinates.
+    pub fn coords(&self) -> &[T] {
+        &self.coords
+    }
+
+    /// Return the number of coordinates in this point.
+    pub fn dimension(&self) -> usize {
+        self.coords.len()
+    }
+
+    /// Return the Manhattan distance between this point and `other`.
+    ///
+    /// The distance is accumulated in a wider type (`i128` for integer
+    /// coordinates), so this only fails if the final distance does not fit
+    /// in a coordinate.
+    pub fn distance(&self, other: &Point<T>) -> Result<T> {
+        let mut distance = T::Wide::ZERO;
+        for (&a, &b) in self.coords.iter().zip(other.coords.iter()) {
+            distance = a
+                .widen()
+                .checked_sub(b.widen())
+                .and_then(|d| distance.checked_add(d.abs()))
+                .ok_or_else(|| self.overflow(other))?;
+        }
+        T::narrow(distance).ok_or_else(|| self.overflow(other))
+    }
+
+    fn overflow(&self, other: &Point<T>) -> Error {
+        Error::Overflow(format!("the distance between {} and {}", self, other))
+    }
+}
+
+// Coordinates are always canonical, so `==` agrees with `total_cmp`.
+impl<T: Coordinate> Eq for Point<T> {}
+
+impl<T: Coordinate> PartialOrd for Point<T> {
+    fn partial_cmp(&self, other: &Point<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Coordinate> Ord for Point<T> {
+    fn cmp(&self, other: &Point<T>) -> Ordering {
+        for (a, b) in self.coords.iter().zip(other.coords.iter()) {
+            match a.total_cmp(b) {
+                Ordering::Equal => {}
+                ordering => return ordering,
+            }
+        }
+        self.coords.len().cmp(&other.coords.len())
+    }
+}
+
+impl<T: Coordinate> Hash for Point<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.coords.len().hash(state);
+        for c in &self.coords {
+            c.hash_into(state);
+        }
+    }
+}
+
+impl<T: Coordinate> fmt::Display for Point<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, c) in self.coords.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Coordinate> FromStr for Point<T> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Point<T>> {
+        Point::from_fields(&fields(s, Syntax::Strict))
+    }
+}
+
+impl<T: Coordinate> Point<T> {
+    fn from_fields(fields: &[(usize, &str)]) -> Result<Point<T>> {
+        let mut coords = vec![];
+        for &(column, field) in fields {
+            coords.push(T::parse_field(column, field)?);
+        }
+        Ok(Point { coords })
+    }
+}
+
+/// The textual format points are written in.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Syntax {
+    /// Exactly one point per line, with coordinates separated by commas.
+    #[default]
+    Strict,
+    /// Like `Strict`, except that coordinates may also be separated by
+    /// semicolons or whitespace, everything after a `#` is ignored, and lines
+    /// that are blank after removing comments contain no point.
+    Lenient,
+}
+
+impl Syntax {
+    /// Parse a single line of input, returning `None` if it holds no point.
+    ///
+    /// Like `Point::from_str`, errors are reported as occurring on line 1.
+    pub fn parse_line<T: Coordinate>(&self, line: &str) -> Result<Option<Point<T>>> {
+        let fields = fields(line, *self);
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        Point::from_fields(&fields).map(Some)
+    }
+}
+
+/// Split a line into its fields, along with the column each field starts at.
+fn fields(line: &str, syntax: Syntax) -> Vec<(usize, &str)> {
+    let column = |field: &str| {
+        let offset = field.as_ptr() as usize - line.as_ptr() as usize;
+        line[..offset].chars().count() + 1
+    };
+    match syntax {
+        Syntax::Strict => line
+            .trim()
+            .split(',')
+            .map(|field| (column(field), field))
+            .collect(),
+        Syntax::Lenient => {
+            let content = match line.find('#') {
+                Some(i) => &line[..i],
+                None => line,
+            };
+            if content.trim().is_empty() {
+                return vec![];
+            }
+            let mut fields = vec![];
+            for part in content.split([',', ';']) {
+                let trimmed = part.trim();
+                if trimmed.is_empty() {
+                    // Keep empty fields, e.g., in `1,,2`, so they're reported.
+                    fields.push((
+                        column(&part[part.len() - part.trim_start().len()..]),
+                        trimmed,
+                    ));
+                }
+                for field in trimmed.split_whitespace() {
+                    fields.push((column(field), field));
+                }
+            }
+            fields
+        }
+    }
+}
+
+/// Return the column at which the field numbered `index` (starting at 0)
+/// begins, or the column just past the end of the line if there are not that
+/// many fields.
+pub(crate) fn column_of_field(line: &str, syntax: Syntax, index: usize) -> usize {
+    if let Some(&(column, _)) = fields(line, syntax).get(index) {
+        return column;
+    }
+    let content = match (syntax, line.find('#')) {
+        (Syntax::Lenient, Some(i)) => &line[..i],
+        _ => line,
+    };
+    content.trim_end().chars().count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lenient_lines() {
+        let cases: [(&str, Option<&[i64]>); 11] = [
+            ("1,2,3", Some(&[1, 2, 3])),
+            ("1 2 3", Some(&[1, 2, 3])),
+            ("1;2;3", Some(&[1, 2, 3])),
+            ("1\t2\t3", Some(&[1, 2, 3])),
+            (" 1 ;2,  3 ", Some(&[1, 2, 3])),
+            ("1, 2 3;4", Some(&[1, 2, 3, 4])),
+            ("-1 2 # a comment, with commas", Some(&[-1, 2])),
+            ("7#", Some(&[7])),
+            ("# only a comment", None),
+            ("   ", None),
+            ("", None),
+        ];
+        for (line, expected) in cases {
+            let point: Option<Point> = Syntax::Lenient.parse_line(line).unwrap();
+            assert_eq!(
+                point,
+                expected.map(|c| Point::new(c.to_vec())),
+                "{:?}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn field_columns() {
+        let check = |line, syntax, expected: &[(usize, &str)]| {
+            assert_eq!(fields(line, syntax), expected, "{:?} {:?}", line, syntax);
+        };
+        check("1,2,3", Syntax::Strict, &[(1, "1"), (3, "2"), (5, "3")]);
+        check("  1,2", Syntax::Strict, &[(3, "1"), (5, "2")]);
+        check("1; 2  3", Syntax::Lenient, &[(1, "1"), (4, "2"), (7, "3")]);
+        check("1,,2", Syntax::Lenient, &[(1, "1"), (3, ""), (4, "2")]);
+        check("1, ;2", Syntax::Lenient, &[(1, "1"), (4, ""), (5, "2")]);
+        check("é;x 3", Syntax::Lenient, &[(1, "é"), (3, "x"), (5, "3")]);
+        check("  # 1,2", Syntax::Lenient, &[]);
+    }
+
+    #[test]
+    fn empty_field_is_reported() {
+        match Syntax::Lenient.parse_line::<i64>("1,,2") {
+            Err(Error::InvalidInt { column, field, .. }) => {
+                assert_eq!((column, field.as_str()), (3, ""));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn column_past_the_end() {
+        assert_eq!(column_of_field("1,2,3", Syntax::Strict, 1), 3);
+        assert_eq!(column_of_field("1,2", Syntax::Strict, 3), 4);
+        assert_eq!(column_of_field("1,2   ", Syntax::Strict, 2), 4);
+        assert_eq!(column_of_field("1 2  # x y z", Syntax::Lenient, 2), 4);
+        assert_eq!(column_of_field("é,ü", Syntax::Lenient, 2), 4);
+    }
+
+    #[test]
+    fn distance_overflow() {
+        let (min, max) = (Point::new(vec![i64::MIN, 0]), Point::new(vec![i64::MAX, 0]));
+        assert!(matches!(min.distance(&max), Err(Error::Overflow(_))));
+        assert!(matches!(max.distance(&min), Err(Error::Overflow(_))));
+        // Each axis fits, but their sum doesn't.
+        let (a, b) = (Point::new(vec![0, 0]), Point::new(vec![i64::MAX, 1]));
+        assert!(matches!(a.distance(&b), Err(Error::Overflow(_))));
+        assert_eq!(
+            Point::new(vec![i64::MIN])
+                .distance(&Point::new(vec![-1]))
+                .unwrap(),
+            i64::MAX
+        );
+
+        let (a, b) = (Point::new(vec![-f64::MAX]), Point::new(vec![f64::MAX]));
+        assert!(matches!(a.distance(&b), Err(Error::Overflow(_))));
+    }
+}
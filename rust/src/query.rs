@@ -0,0 +1,297 @@
+use std::cmp::Ordering;
+
+use crate::{Coordinate, Grid, Metric, Point, Wide};
+
+/// A fixed set of points, indexed for radius and nearest-neighbor queries.
+///
+/// Queries return the indices of the points found, along with their raw
+/// distances from the query point (see `Metric::raw_distance`). Results are
+/// ordered by distance, then by point, then by index. A point whose distance
+/// from the query point is too large to represent is never found.
+#[derive(Clone, Debug)]
+pub struct PointSet<T = i64> {
+    points: Vec<Point<T>>,
+    metric: Metric,
+    cell_size: T,
+    grid: Grid<T>,
+}
+
+impl<T: Coordinate> PointSet<T> {
+    /// Index `points`, which must all have the same number of coordinates,
+    /// for queries in `metric`.
+    pub fn new(points: Vec<Point<T>>, metric: Metric) -> PointSet<T> {
+        let cell_size = cell_size(&points);
+        let mut grid = Grid::new(cell_size);
+        for (i, p) in points.iter().enumerate() {
+            grid.insert(i, p);
+        }
+        PointSet {
+            points,
+            metric,
+            cell_size,
+            grid,
+        }
+    }
+
+    /// Return the indexed points, in the order they were given.
+    pub fn points(&self) -> &[Point<T>] {
+        &self.points
+    }
+
+    /// Return the metric distances are measured in.
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Return the number of indexed points.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Return true if there are no indexed points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Return every point at most `radius` from `query`, which must have the
+    /// same number of coordinates as the indexed points.
+    pub fn within(&self, query: &Point<T>, radius: T) -> Vec<(usize, T::Wide)> {
+        let mut found = self
+            .unsorted_within(query, radius)
+            .unwrap_or_else(|| self.all(query, self.limit(radius)));
+        self.sort(&mut found);
+        found
+    }
+
+    /// Return the `k` points nearest `query`, which must have the same number
+    /// of coordinates as the indexed points. Fewer are returned if there are
+    /// not `k` points.
+    pub fn nearest(&self, query: &Point<T>, k: usize) -> Vec<(usize, T::Wide)> {
+        if k == 0 {
+            return vec![];
+        }
+        // Search ever larger radii until one holds at least `k` points. Every
+        // point nearer than the `k`-th is then sure to have been found.
+        let mut radius = self.cell_size;
+        let mut found = loop {
+            if k >= self.points.len() {
+                break self.all(query, T::Wide::MAX);
+            }
+            match self.unsorted_within(query, radius) {
+                Some(found) if found.len() >= k => break found,
+                Some(_) => {}
+                None => break self.all(query, T::Wide::MAX),
+            }
+            let next = radius.saturating_add(radius);
+            if next <= radius {
+                break self.all(query, T::Wide::MAX);
+            }
+            radius = next;
+        };
+        if found.len() > k {
+            found.select_nth_unstable_by(k - 1, |a, b| self.compare(a, b));
+            found.truncate(k);
+        }
+        self.sort(&mut found);
+        found
+    }
+
+    /// Return every point at most `radius` from `query`, in no particular
+    /// order, or `None` if that would mean looking in more grid cells than
+    /// there are points.
+    fn unsorted_within(&self, query: &Point<T>, radius: T) -> Option<Vec<(usize, T::Wide)>> {
+        let axis_radius = self.metric.axis_radius(radius);
+        if self.grid.span(query, axis_radius) > self.points.len() as u128 {
+            return None;
+        }
+        let limit = self.limit(radius);
+        let mut found = vec![];
+        self.grid.for_each_candidate(query, axis_radius, |i| {
+            match self.metric.raw_distance(query, &self.points[i]) {
+                Some(distance) if distance <= limit => found.push((i, distance)),
+                _ => {}
+            }
+        });
+        Some(found)
+    }
+
+    /// Return every point whose raw distance from `query` is at most `limit`,
+    /// in no particular order.
+    fn all(&self, query: &Point<T>, limit: T::Wide) -> Vec<(usize, T::Wide)> {
+        self.points
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| Some((i, self.metric.raw_distance(query, p)?)))
+            .filter(|&(_, distance)| distance <= limit)
+            .collect()
+    }
+
+    /// Return `radius` in raw form. A radius too large to represent reaches
+    /// every distance that isn't.
+    fn limit(&self, radius: T) -> T::Wide {
+        self.metric.raw_limit(radius).unwrap_or(T::Wide::MAX)
+    }
+
+    fn sort(&self, found: &mut [(usize, T::Wide)]) {
+        found.sort_by(|a, b| self.compare(a, b));
+    }
+
+    fn compare(&self, &(i, a): &(usize, T::Wide), &(j, b): &(usize, T::Wide)) -> Ordering {
+        a.partial_cmp(&b)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.points[i].cmp(&self.points[j]))
+            .then(i.cmp(&j))
+    }
+}
+
+/// Pick a grid cell width that puts about one point in each cell, supposing
+/// the points are spread evenly over their bounding box.
+fn cell_size<T: Coordinate>(points: &[Point<T>]) -> T {
+    let Some(first) = points.first() else {
+        return T::from(1);
+    };
+    // The volume is kept as a logarithm, since it can easily be too large for
+    // an `f64`. Axes on which every point is the same are left out.
+    let mut log_volume = 0.0;
+    let mut axes = 0;
+    for axis in 0..first.dimension() {
+        let coords = points.iter().map(|p| p.coords()[axis].widen().to_f64());
+        let lo = coords.clone().fold(f64::INFINITY, f64::min);
+        let hi = coords.fold(f64::NEG_INFINITY, f64::max);
+        if hi > lo {
+            log_volume += (hi - lo).ln();
+            axes += 1;
+        }
+    }
+    if axes == 0 {
+        return T::from(1);
+    }
+    let log_size = (log_volume - (points.len() as f64).ln()) / axes as f64;
+    T::approximate(log_size.exp()).cell_size()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A xorshift generator, so that the tests are repeatable and need no
+    /// dependencies.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Return `n` points with `dimension` coordinates from `-span` to
+        /// `span`. Small spans give plenty of duplicates.
+        fn points(&mut self, n: usize, dimension: usize, span: i64) -> Vec<Point> {
+            let width = (2 * span + 1) as u64;
+            (0..n)
+                .map(|_| {
+                    Point::new(
+                        (0..dimension)
+                            .map(|_| (self.next() % width) as i64 - span)
+                            .collect(),
+                    )
+                })
+                .collect()
+        }
+    }
+
+    const METRICS: [Metric; 4] = [
+        Metric::Manhattan,
+        Metric::Chebyshev,
+        Metric::SquaredEuclidean,
+        Metric::Minkowski(3),
+    ];
+
+    /// Every point sorted by distance from `query`, then by point and index.
+    fn brute_force(set: &PointSet, query: &Point) -> Vec<(usize, i128)> {
+        let mut found: Vec<(usize, i128)> = set
+            .points()
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i, set.metric().raw_distance(query, p).unwrap()))
+            .collect();
+        found.sort_by(|&(i, a), &(j, b)| (a, &set.points()[i], i).cmp(&(b, &set.points()[j], j)));
+        found
+    }
+
+    #[test]
+    fn within_matches_brute_force() {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        for metric in METRICS {
+            for (n, span) in [(1, 5), (40, 2), (300, 50), (300, 100000)] {
+                let set = PointSet::new(rng.points(n, 3, span), metric);
+                for query in rng.points(10, 3, span + 2) {
+                    let all = brute_force(&set, &query);
+                    for radius in [0, 1, 3, 20, span, 4 * span] {
+                        let limit = metric.raw_limit(radius).unwrap();
+                        let expected: Vec<(usize, i128)> =
+                            all.iter().cloned().filter(|&(_, d)| d <= limit).collect();
+                        assert_eq!(
+                            set.within(&query, radius),
+                            expected,
+                            "{:?} radius {} around {}",
+                            metric,
+                            radius,
+                            query
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+        for metric in METRICS {
+            for (n, span) in [(1, 5), (40, 2), (300, 50), (300, 100000)] {
+                let set = PointSet::new(rng.points(n, 2, span), metric);
+                for query in rng.points(10, 2, 2 * span) {
+                    let all = brute_force(&set, &query);
+                    for k in [0, 1, 2, 7, n - 1, n, n + 3] {
+                        let expected = &all[..k.min(n)];
+                        assert_eq!(
+                            set.nearest(&query, k),
+                            expected,
+                            "{:?} {} nearest {}",
+                            metric,
+                            k,
+                            query
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn duplicates_are_ordered_by_index() {
+        let points = vec![
+            Point::new(vec![1, 1]),
+            Point::new(vec![0, 0]),
+            Point::new(vec![1, 1]),
+        ];
+        let set = PointSet::new(points, Metric::Manhattan);
+        let query = Point::new(vec![1, 1]);
+        assert_eq!(set.within(&query, 0), vec![(0, 0), (2, 0)]);
+        assert_eq!(set.nearest(&query, 2), vec![(0, 0), (2, 0)]);
+        assert_eq!(set.nearest(&query, 3), vec![(0, 0), (2, 0), (1, 2)]);
+    }
+
+    #[test]
+    fn empty_set() {
+        let set: PointSet = PointSet::new(vec![], Metric::Chebyshev);
+        let query = Point::new(vec![3, 4]);
+        assert!(set.is_empty());
+        assert_eq!(set.within(&query, 10), vec![]);
+        assert_eq!(set.nearest(&query, 0), vec![]);
+        assert_eq!(set.nearest(&query, 5), vec![]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::io::BufRead;
+use std::marker::PhantomData;
+
+use crate::{point, Coordinate, Error, Point, Result, Syntax};
+
+/// An iterator over the points in a buffered reader, parsed one line at a
+/// time.
+///
+/// Only the line currently being parsed is held in memory. Invalid lines are
+/// yielded as errors, after which iteration may continue with the next line.
+/// The number of coordinates every point must have is taken from the first
+/// valid point. Coordinates are parsed as `T`.
+#[derive(Debug)]
+pub struct PointReader<R, T = i64> {
+    rdr: R,
+    syntax: Syntax,
+    line: String,
+    lineno: usize,
+    dimension: Option<usize>,
+    coordinate: PhantomData<T>,
+}
+
+impl<R: BufRead, T: Coordinate> PointReader<R, T> {
+    /// Create a reader of points written in `syntax`.
+    pub fn new(rdr: R, syntax: Syntax) -> PointReader<R, T> {
+        PointReader {
+            rdr,
+            syntax,
+            line: String::new(),
+            lineno: 0,
+            dimension: None,
+            coordinate: PhantomData,
+        }
+    }
+
+    /// Return the text of the line most recently read, without its line
+    /// terminator.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Return the number of the line most recently read, starting at 1.
+    pub fn line_number(&self) -> usize {
+        self.lineno
+    }
+
+    /// Return the number of coordinates every point must have, if it is known
+    /// yet.
+    pub fn dimension(&self) -> Option<usize> {
+        self.dimension
+    }
+
+    /// Require every point to have `dimension` coordinates, instead of taking
+    /// that number from the first valid point.
+    pub fn set_dimension(&mut self, dimension: usize) {
+        self.dimension = Some(dimension);
+    }
+
+    fn parse_line(&self) -> Result<Option<Point<T>>> {
+        let point = match self.syntax.parse_line(&self.line) {
+            Ok(Some(point)) => point,
+            Ok(None) => return Ok(None),
+            Err(err) => return Err(err.on_line(self.lineno)),
+        };
+        if let Some(expected) = self.dimension {
+            if point.dimension() != expected {
+                return Err(Error::Arity {
+                    line: self.lineno,
+                    column: point::column_of_field(&self.line, self.syntax, expected),
+                    expected,
+                    found: point.dimension(),
+                });
+            }
+        }
+        Ok(Some(point))
+    }
+}
+
+impl<R: BufRead, T: Coordinate> Iterator for PointReader<R, T> {
+    type Item = Result<Point<T>>;
+
+    fn next(&mut self) -> Option<Result<Point<T>>> {
+        loop {
+            self.line.clear();
+            match self.rdr.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => return Some(Err(Error::Io(err))),
+            }
+            self.lineno += 1;
+            if self.line.ends_with('\n') {
+                self.line.pop();
+                if self.line.ends_with('\r') {
+                    self.line.pop();
+                }
+            }
+            match self.parse_line() {
+                Ok(Some(point)) => {
+                    self.dimension = Some(point.dimension());
+                    return Some(Ok(point));
+                }
+                Ok(None) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
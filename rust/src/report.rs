@@ -0,0 +1,825 @@
+// Printing the results of a command in each of the supported output formats.
+
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use constellation::{
+    Constellations, Coordinate, Dendrogram, Merge, Metric, Point, PointSet, Stats, Summary, Wide,
+};
+
+use crate::{Args, CliError, Command, Result};
+
+/// An output format for results.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+    Ndjson,
+}
+
+impl FromStr for Format {
+    type Err = CliError;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "ndjson" => Ok(Format::Ndjson),
+            _ => Err(CliError::Usage(format!(
+                "unrecognized format '{}' (expected text, json, csv or ndjson)",
+                s
+            ))),
+        }
+    }
+}
+
+/// What the query command looks for around each query point.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Search<T> {
+    /// The points connected to it by the threshold.
+    Connected,
+    /// The points at most this far from it.
+    Radius(T),
+    /// This many of the points nearest it.
+    Nearest(usize),
+}
+
+/// Writes the results for one or more inputs to stdout.
+///
+/// With several inputs, JSON output is an array with one element per input,
+/// and CSV output has a single header with an extra `input` column.
+#[derive(Debug)]
+pub struct Reporter<'a, T> {
+    args: &'a Args,
+    /// The points to query, for the query command.
+    queries: Vec<Point<T>>,
+    /// What to look for around each query point.
+    search: Search<T>,
+    /// True if results will be labelled by the input they came from.
+    labelled: bool,
+    /// The number of results reported so far.
+    reported: usize,
+    /// The number of coordinate columns in the CSV header, once written.
+    csv_dimension: Option<usize>,
+}
+
+impl<'a, T: Coordinate> Reporter<'a, T> {
+    pub fn new(
+        args: &'a Args,
+        queries: Vec<Point<T>>,
+        search: Search<T>,
+        labelled: bool,
+    ) -> Reporter<'a, T> {
+        Reporter {
+            args,
+            queries,
+            search,
+            labelled,
+            reported: 0,
+            csv_dimension: None,
+        }
+    }
+
+    /// Print the result of running the command in `args` on `consts`, which
+    /// came from the input named `label`.
+    pub fn report(&mut self, label: &str, consts: &Constellations<T>) -> Result<()> {
+        let label = if self.labelled { Some(label) } else { None };
+        if matches!(
+            self.args.command,
+            Command::Query | Command::Lookup | Command::Explain
+        ) {
+            self.check_queries(consts)?;
+        }
+        let mut out = io::stdout().lock();
+        match self.args.format {
+            Format::Text => self.text(&mut out, label, consts)?,
+            Format::Json => {
+                if self.labelled {
+                    write!(out, "{}", if self.reported == 0 { "[\n" } else { ",\n" })?;
+                }
+                write!(out, "{{")?;
+                if let Some(label) = label {
+                    write!(out, "\"input\":{},", json_string(label))?;
+                }
+                write!(out, "\"count\":{}", consts.len())?;
+                if self.args.command == Command::List {
+                    write!(out, ",\"constellations\":[")?;
+                    for (id, group) in consts.groups().iter().enumerate() {
+                        if id > 0 {
+                            write!(out, ",")?;
+                        }
+                        write!(out, "{}", json_constellation(None, id, group.points()))?;
+                    }
+                    write!(out, "]")?;
+                }
+                if self.args.command == Command::Label {
+                    write!(out, ",\"labels\":[")?;
+                    for (index, (id, p)) in consts.labelled().into_iter().enumerate() {
+                        if index > 0 {
+                            write!(out, ",")?;
+                        }
+                        write!(out, "{}", json_label(None, index, id, p))?;
+                    }
+                    write!(out, "]")?;
+                }
+                if self.args.command == Command::Stats {
+                    let stats = consts.stats()?;
+                    write!(out, ",{}", json_aggregate(&stats))?;
+                    let groups: Vec<String> = stats
+                        .groups
+                        .iter()
+                        .enumerate()
+                        .map(|(id, summary)| json_summary(None, Some(id), summary))
+                        .collect();
+                    write!(out, ",\"constellations\":[{}]", groups.join(","))?;
+                }
+                if self.args.command == Command::Lookup {
+                    let lookups: Vec<String> = self
+                        .queries
+                        .iter()
+                        .zip(consts.lookup_all(&self.queries))
+                        .map(|(query, ids)| json_lookup(None, query, &ids))
+                        .collect();
+                    write!(out, ",\"lookups\":[{}]", lookups.join(","))?;
+                }
+                write!(out, "}}")?;
+                if !self.labelled {
+                    writeln!(out)?;
+                }
+            }
+            Format::Ndjson => {
+                if self.args.command == Command::Count {
+                    let input = match label {
+                        Some(label) => format!("\"input\":{},", json_string(label)),
+                        None => String::new(),
+                    };
+                    writeln!(out, "{{{}\"count\":{}}}", input, consts.len())?;
+                } else if self.args.command == Command::Label {
+                    for (index, (id, p)) in consts.labelled().into_iter().enumerate() {
+                        writeln!(out, "{}", json_label(label, index, id, p))?;
+                    }
+                } else if self.args.command == Command::Stats {
+                    // The whole input comes first, with a null id, then each
+                    // constellation.
+                    let stats = consts.stats()?;
+                    let input = match label {
+                        Some(label) => format!("\"input\":{},", json_string(label)),
+                        None => String::new(),
+                    };
+                    writeln!(
+                        out,
+                        "{{{}\"id\":null,\"count\":{},{}}}",
+                        input,
+                        stats.constellations,
+                        json_aggregate(&stats)
+                    )?;
+                    for (id, summary) in stats.groups.iter().enumerate() {
+                        writeln!(out, "{}", json_summary(label, Some(id), summary))?;
+                    }
+                } else if self.args.command == Command::Lookup {
+                    for (query, ids) in self.queries.iter().zip(consts.lookup_all(&self.queries)) {
+                        writeln!(out, "{}", json_lookup(label, query, &ids))?;
+                    }
+                } else {
+                    for (id, group) in consts.groups().iter().enumerate() {
+                        writeln!(out, "{}", json_constellation(label, id, group.points()))?;
+                    }
+                }
+            }
+            Format::Csv => self.csv(&mut out, label, consts)?,
+        }
+        self.reported += 1;
+        Ok(())
+    }
+
+    /// Finish any output that spans all of the results reported.
+    pub fn finish(&mut self) -> Result<()> {
+        let mut out = io::stdout().lock();
+        if self.args.format == Format::Json && self.labelled {
+            if self.reported == 0 {
+                write!(out, "[")?;
+            }
+            writeln!(out, "\n]")?;
+        }
+        // The CSV header waits for an input with points. If every input was
+        // empty, there are no coordinate columns.
+        let per_point = matches!(
+            self.args.command,
+            Command::List | Command::Label | Command::Stats
+        );
+        if self.args.format == Format::Csv
+            && per_point
+            && self.reported > 0
+            && self.csv_dimension.is_none()
+        {
+            writeln!(out, "{}", self.csv_header(0))?;
+        }
+        Ok(())
+    }
+
+    /// Print the single-linkage merge table of `points`, which came from the
+    /// input named `label`, for the linkage command.
+    pub fn report_linkage(&mut self, label: &str, points: &[Point<T>]) -> Result<()> {
+        let label = if self.labelled { Some(label) } else { None };
+        let dendrogram = Dendrogram::new(points, self.args.metric)?;
+        let n = dendrogram.num_points();
+        let mut out = io::stdout().lock();
+        match self.args.format {
+            Format::Text => {
+                if let Some(label) = label {
+                    writeln!(out, "==> {} <==", label)?;
+                }
+                writeln!(out, "points: {}", n)?;
+                for (i, merge) in dendrogram.merges().iter().enumerate() {
+                    writeln!(
+                        out,
+                        "cluster {}: {} + {} at distance {} ({} points, {} constellations)",
+                        n + i,
+                        merge.left,
+                        merge.right,
+                        text_distance(self.args.metric, merge.distance),
+                        merge.size,
+                        n - i - 1
+                    )?;
+                }
+            }
+            Format::Json => {
+                if self.labelled {
+                    write!(out, "{}", if self.reported == 0 { "[\n" } else { ",\n" })?;
+                }
+                write!(out, "{{")?;
+                if let Some(label) = label {
+                    write!(out, "\"input\":{},", json_string(label))?;
+                }
+                let merges: Vec<String> = dendrogram
+                    .merges()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, merge)| self.json_merge(None, &dendrogram, i, merge))
+                    .collect();
+                write!(out, "\"points\":{},\"merges\":[{}]}}", n, merges.join(","))?;
+                if !self.labelled {
+                    writeln!(out)?;
+                }
+            }
+            Format::Ndjson => {
+                for (i, merge) in dendrogram.merges().iter().enumerate() {
+                    writeln!(out, "{}", self.json_merge(label, &dendrogram, i, merge))?;
+                }
+            }
+            Format::Csv => {
+                if self.reported == 0 {
+                    writeln!(
+                        out,
+                        "{}id,left,right,distance,size,constellations",
+                        if label.is_some() { "input," } else { "" }
+                    )?;
+                }
+                let input = match label {
+                    Some(label) => format!("{},", csv_field(label)),
+                    None => String::new(),
+                };
+                for (i, merge) in dendrogram.merges().iter().enumerate() {
+                    writeln!(
+                        out,
+                        "{}{},{},{},{},{},{}",
+                        input,
+                        n + i,
+                        merge.left,
+                        merge.right,
+                        text_distance(self.args.metric, merge.distance),
+                        merge.size,
+                        n - i - 1
+                    )?;
+                }
+            }
+        }
+        self.reported += 1;
+        Ok(())
+    }
+
+    /// Render the merge at index `i` of `dendrogram` as a JSON object.
+    fn json_merge(
+        &self,
+        input: Option<&str>,
+        dendrogram: &Dendrogram<T>,
+        i: usize,
+        merge: &Merge<T>,
+    ) -> String {
+        let input = match input {
+            Some(input) => format!("\"input\":{},", json_string(input)),
+            None => String::new(),
+        };
+        let n = dendrogram.num_points();
+        format!(
+            "{{{}\"id\":{},\"left\":{},\"right\":{},\"distance\":{},\"size\":{},\
+             \"constellations\":{}}}",
+            input,
+            n + i,
+            merge.left,
+            merge.right,
+            text_distance(self.args.metric, merge.distance),
+            merge.size,
+            n - i - 1
+        )
+    }
+
+    /// Check that every query point has as many coordinates as the points in
+    /// `consts`.
+    fn check_queries(&self, consts: &Constellations<T>) -> Result<()> {
+        let Some(dimension) = consts.groups().first().map(|g| g.points()[0].dimension()) else {
+            return Ok(());
+        };
+        for query in &self.queries {
+            if query.dimension() != dimension {
+                return Err(CliError::Input(format!(
+                    "query point {} has {} coordinates, but the input points have {}",
+                    query,
+                    query.dimension(),
+                    dimension
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn text<W: Write>(
+        &mut self,
+        mut out: W,
+        label: Option<&str>,
+        consts: &Constellations<T>,
+    ) -> Result<()> {
+        if self.args.command == Command::Count {
+            match label {
+                None => writeln!(out, "constellations: {}", consts.len())?,
+                Some(label) => writeln!(out, "{}: constellations: {}", label, consts.len())?,
+            }
+            return Ok(());
+        }
+
+        if let Some(label) = label {
+            writeln!(out, "==> {} <==", label)?;
+        }
+        match self.args.command {
+            Command::Count | Command::Linkage | Command::Validate => unreachable!(),
+            Command::Label => {
+                for (id, p) in consts.labelled() {
+                    writeln!(out, "{} {}", p, id)?;
+                }
+            }
+            Command::List => {
+                for (i, group) in consts.groups().iter().enumerate() {
+                    writeln!(out, "constellation {} ({} points)", i, group.points().len())?;
+                    for p in group.points() {
+                        writeln!(out, "  {}", p)?;
+                    }
+                }
+            }
+            Command::Stats => {
+                let stats = consts.stats()?;
+                writeln!(out, "points: {}", stats.points)?;
+                writeln!(out, "constellations: {}", stats.constellations)?;
+                writeln!(out, "largest: {}", stats.largest)?;
+                writeln!(out, "smallest: {}", stats.smallest)?;
+                writeln!(out, "singletons: {}", stats.singletons)?;
+                if let Some(ref overall) = stats.overall {
+                    writeln!(out, "diameter: {}", overall.diameter)?;
+                    writeln!(out, "bounding box: {} to {}", overall.min, overall.max)?;
+                    writeln!(out, "centroid: {}", text_centroid(&overall.centroid))?;
+                }
+                writeln!(out, "sizes:")?;
+                for (size, count) in &stats.histogram {
+                    writeln!(out, "  {}: {}", size, count)?;
+                }
+                for (i, summary) in stats.groups.iter().enumerate() {
+                    writeln!(
+                        out,
+                        "constellation {} ({} points): diameter {}, bounding box {} to {}, \
+                         centroid {}",
+                        i,
+                        summary.size,
+                        summary.diameter,
+                        summary.min,
+                        summary.max,
+                        text_centroid(&summary.centroid)
+                    )?;
+                }
+            }
+            Command::Query => {
+                let labelled = consts.labelled();
+                // The points are indexed once, in input order, and shared by
+                // every query.
+                let set = match self.search {
+                    Search::Connected => None,
+                    _ => Some(PointSet::new(
+                        labelled.iter().map(|&(_, p)| p.clone()).collect(),
+                        self.args.metric,
+                    )),
+                };
+                let mut connected = match self.search {
+                    Search::Connected => consts.connected_all(&self.queries),
+                    _ => vec![],
+                }
+                .into_iter();
+                for query in &self.queries {
+                    let found = match (self.search, &set) {
+                        (Search::Radius(radius), Some(set)) => {
+                            let found = set.within(query, radius);
+                            writeln!(out, "{}: {} points within {}", query, found.len(), radius)?;
+                            found
+                        }
+                        (Search::Nearest(k), Some(set)) => {
+                            let found = set.nearest(query, k);
+                            writeln!(out, "{}: {} nearest points", query, found.len())?;
+                            found
+                        }
+                        _ => {
+                            let found = connected.next().unwrap();
+                            writeln!(out, "{}: {} connected points", query, found.len())?;
+                            for (group, p) in found {
+                                writeln!(out, "  {} (constellation {})", p, group)?;
+                            }
+                            continue;
+                        }
+                    };
+                    for (i, distance) in found {
+                        let (group, p) = labelled[i];
+                        writeln!(
+                            out,
+                            "  {} (constellation {}, distance {})",
+                            p,
+                            group,
+                            text_distance(self.args.metric, distance)
+                        )?;
+                    }
+                }
+            }
+            Command::Explain => {
+                let (a, b) = (&self.queries[0], &self.queries[1]);
+                let labelled = consts.labelled();
+                let find = |query| labelled.iter().position(|&(_, p)| p == query);
+                let (from, to) = match (find(a), find(b)) {
+                    (Some(from), Some(to)) => (from, to),
+                    (from, _) => {
+                        let missing = if from.is_none() { a } else { b };
+                        writeln!(out, "{} is not in the input", missing)?;
+                        return Ok(());
+                    }
+                };
+                let Some(path) = consts.path(from, to) else {
+                    writeln!(
+                        out,
+                        "{} and {} are in different constellations ({} and {})",
+                        a, b, labelled[from].0, labelled[to].0
+                    )?;
+                    return Ok(());
+                };
+                writeln!(
+                    out,
+                    "{} and {} are both in constellation {}, linked by {} hops:",
+                    a,
+                    b,
+                    labelled[from].0,
+                    path.len() - 1
+                )?;
+                writeln!(out, "  {}", a)?;
+                for hop in path.windows(2) {
+                    let (prev, next) = (labelled[hop[0]].1, labelled[hop[1]].1);
+                    // Connected points are never too far apart to measure.
+                    let distance = self.args.metric.raw_distance(prev, next).unwrap();
+                    writeln!(
+                        out,
+                        "  {} (distance {})",
+                        next,
+                        text_distance(self.args.metric, distance)
+                    )?;
+                }
+            }
+            Command::Lookup => {
+                for (query, ids) in self.queries.iter().zip(consts.lookup_all(&self.queries)) {
+                    match ids.len() {
+                        0 => writeln!(out, "{}: no constellation", query)?,
+                        1 => writeln!(out, "{}: constellation {}", query, ids[0])?,
+                        _ => {
+                            let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                            writeln!(
+                                out,
+                                "{}: constellations {} (would merge)",
+                                query,
+                                ids.join(", ")
+                            )?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn csv<W: Write>(
+        &mut self,
+        mut out: W,
+        label: Option<&str>,
+        consts: &Constellations<T>,
+    ) -> Result<()> {
+        let input = match label {
+            Some(label) => format!("{},", csv_field(label)),
+            None => String::new(),
+        };
+        if self.args.command == Command::Count {
+            if self.reported == 0 {
+                writeln!(out, "{}count", if label.is_some() { "input," } else { "" })?;
+            }
+            writeln!(out, "{}{}", input, consts.len())?;
+            return Ok(());
+        }
+
+        // An input with no points has no rows, and says nothing about how
+        // many coordinate columns there should be.
+        let Some(dimension) = consts.groups().first().map(|g| g.points()[0].dimension()) else {
+            return Ok(());
+        };
+        match self.csv_dimension {
+            None => {
+                self.csv_dimension = Some(dimension);
+                writeln!(out, "{}", self.csv_header(dimension))?;
+            }
+            Some(expected) if expected != dimension => {
+                return Err(CliError::Input(format!(
+                    "CSV output needs every input to have the same number of \
+                     coordinates, but {} has {} instead of {}",
+                    label.unwrap_or("<stdin>"),
+                    dimension,
+                    expected
+                )));
+            }
+            Some(_) => {}
+        }
+        if self.args.command == Command::Label {
+            for (index, (id, p)) in consts.labelled().into_iter().enumerate() {
+                writeln!(out, "{}{},{},{}", input, index, id, p)?;
+            }
+            return Ok(());
+        }
+        if self.args.command == Command::Stats {
+            // The whole input comes first, in a row with no constellation,
+            // then each constellation. The size histogram and singletons
+            // follow from the constellations' sizes.
+            let stats = consts.stats()?;
+            let rows = stats.overall.iter().map(|s| (String::new(), s));
+            let groups = stats.groups.iter().enumerate();
+            for (id, summary) in rows.chain(groups.map(|(id, s)| (id.to_string(), s))) {
+                let centroid: Vec<String> =
+                    summary.centroid.iter().map(|c| c.to_string()).collect();
+                writeln!(
+                    out,
+                    "{}{},{},{},{},{},{}",
+                    input,
+                    id,
+                    summary.size,
+                    summary.diameter,
+                    summary.min,
+                    summary.max,
+                    centroid.join(",")
+                )?;
+            }
+            return Ok(());
+        }
+        for (id, group) in consts.groups().iter().enumerate() {
+            for p in group.points() {
+                writeln!(out, "{}{},{}", input, id, p)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the CSV header for points with `dimension` coordinates.
+    fn csv_header(&self, dimension: usize) -> String {
+        let mut header = String::new();
+        if self.labelled {
+            header.push_str("input,");
+        }
+        if self.args.command == Command::Label {
+            header.push_str("index,");
+        }
+        header.push_str("constellation");
+        if self.args.command == Command::Stats {
+            header.push_str(",size,diameter");
+            for prefix in ["min_", "max_", "centroid_"] {
+                for i in 0..dimension {
+                    header.push_str(&format!(",{}x{}", prefix, i));
+                }
+            }
+        } else {
+            for i in 0..dimension {
+                header.push_str(&format!(",x{}", i));
+            }
+        }
+        header
+    }
+}
+
+/// Render a constellation as a JSON object, including the input it came from
+/// if there is one.
+fn json_constellation<T: Coordinate>(
+    input: Option<&str>,
+    id: usize,
+    points: &[Point<T>],
+) -> String {
+    let input = match input {
+        Some(input) => format!("\"input\":{},", json_string(input)),
+        None => String::new(),
+    };
+    let points: Vec<String> = points.iter().map(|p| format!("[{}]", p)).collect();
+    format!(
+        "{{{}\"id\":{},\"size\":{},\"points\":[{}]}}",
+        input,
+        id,
+        points.len(),
+        points.join(",")
+    )
+}
+
+/// Render the constellation a point is in as a JSON object. `index` is the
+/// point's position in the input.
+fn json_label<T: Coordinate>(
+    input: Option<&str>,
+    index: usize,
+    id: usize,
+    point: &Point<T>,
+) -> String {
+    let input = match input {
+        Some(input) => format!("\"input\":{},", json_string(input)),
+        None => String::new(),
+    };
+    format!(
+        "{{{}\"index\":{},\"point\":[{}],\"constellation\":{}}}",
+        input, index, point, id
+    )
+}
+
+/// Render the constellations a query point would join as a JSON object.
+fn json_lookup<T: Coordinate>(input: Option<&str>, query: &Point<T>, ids: &[usize]) -> String {
+    let input = match input {
+        Some(input) => format!("\"input\":{},", json_string(input)),
+        None => String::new(),
+    };
+    let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+    format!(
+        "{{{}\"point\":[{}],\"constellations\":[{}],\"merge\":{}}}",
+        input,
+        query,
+        ids.join(","),
+        ids.len() > 1
+    )
+}
+
+/// Render a summary of a set of points as a JSON object. `id` is the
+/// constellation the summary is of, if it is of a single constellation.
+/// Render the statistics about a whole input as the fields of a JSON object,
+/// without the enclosing braces.
+fn json_aggregate<T: Coordinate>(stats: &Stats<T>) -> String {
+    let histogram: Vec<String> = stats
+        .histogram
+        .iter()
+        .map(|(size, count)| format!("{{\"size\":{},\"count\":{}}}", size, count))
+        .collect();
+    let overall = match stats.overall {
+        Some(ref overall) => json_summary(None, None, overall),
+        None => "null".to_string(),
+    };
+    format!(
+        "\"points\":{},\"singletons\":{},\"largest\":{},\"smallest\":{},\
+         \"histogram\":[{}],\"overall\":{}",
+        stats.points,
+        stats.singletons,
+        stats.largest,
+        stats.smallest,
+        histogram.join(","),
+        overall
+    )
+}
+
+fn json_summary<T: Coordinate>(
+    input: Option<&str>,
+    id: Option<usize>,
+    summary: &Summary<T>,
+) -> String {
+    let mut prefix = match input {
+        Some(input) => format!("\"input\":{},", json_string(input)),
+        None => String::new(),
+    };
+    if let Some(id) = id {
+        prefix.push_str(&format!("\"id\":{},", id));
+    }
+    let centroid: Vec<String> = summary.centroid.iter().map(|c| c.to_string()).collect();
+    format!(
+        "{{{}\"size\":{},\"min\":[{}],\"max\":[{}],\"centroid\":[{}],\"diameter\":{}}}",
+        prefix,
+        summary.size,
+        summary.min,
+        summary.max,
+        centroid.join(","),
+        summary.diameter
+    )
+}
+
+/// Render a centroid for people to read, to three decimal places.
+fn text_centroid(centroid: &[f64]) -> String {
+    let coords: Vec<String> = centroid.iter().map(|c| format!("{:.3}", c)).collect();
+    coords.join(",")
+}
+
+/// Render a raw distance in `metric` (see `Metric::raw_distance`) as the
+/// actual distance.
+fn text_distance<W: Wide>(metric: Metric, raw: W) -> String {
+    match metric {
+        Metric::Minkowski(p) if p > 1 => raw.to_f64().powf(1.0 / p as f64).to_string(),
+        _ => raw.to_string(),
+    }
+}
+
+/// Render a string as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Quote a CSV field if it needs it.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parsed;
+    use constellation::Threshold;
+
+    fn args(args: &[&str]) -> Args {
+        match Args::parse(args.iter().map(|arg| arg.to_string())) {
+            Ok(Parsed::Run(args)) => args,
+            _ => panic!("bad arguments {:?}", args),
+        }
+    }
+
+    fn points(points: &[[i64; 2]]) -> Constellations {
+        let points: Vec<Point> = points.iter().map(|p| Point::new(p.to_vec())).collect();
+        Constellations::cluster(&points, Threshold::default())
+    }
+
+    #[test]
+    fn csv_header_waits_for_points() {
+        let args = args(&["list", "-f", "csv", "empty.txt", "a.txt", "b.txt"]);
+        let mut reporter = Reporter::new(&args, vec![], Search::Connected, true);
+        let mut out = vec![];
+        reporter
+            .csv(&mut out, Some("empty.txt"), &points(&[]))
+            .unwrap();
+        reporter
+            .csv(&mut out, Some("a.txt"), &points(&[[0, 0], [9, 9]]))
+            .unwrap();
+        reporter.csv(&mut out, Some("b.txt"), &points(&[])).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "input,constellation,x0,x1\na.txt,0,0,0\na.txt,1,9,9\n"
+        );
+    }
+
+    #[test]
+    fn csv_stats_start_with_the_whole_input() {
+        let args = args(&["stats", "-f", "csv"]);
+        let mut reporter = Reporter::new(&args, vec![], Search::Connected, false);
+        let mut out = vec![];
+        let consts = points(&[[0, 0], [1, 1], [9, 9]]);
+        reporter.csv(&mut out, None, &consts).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = out.lines().skip(1).collect();
+        assert_eq!(
+            rows,
+            vec![
+                ",3,18,0,0,9,9,3.3333333333333335,3.3333333333333335",
+                "0,2,2,0,0,1,1,0.5,0.5",
+                "1,1,0,9,9,9,9,9,9",
+            ]
+        );
+    }
+}
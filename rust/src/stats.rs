@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+
+use crate::{Constellations, Coordinate, Error, Point, Result, Wide};
+
+/// Summary statistics about a set of constellations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats<T = i64> {
+    /// The number of points in all constellations.
+    pub points: usize,
+    /// The number of constellations.
+    pub constellations: usize,
+    /// The number of constellations with only one point.
+    pub singletons: usize,
+    /// The number of points in the largest constellation, or 0 if there are
+    /// none.
+    pub largest: usize,
+    /// The number of points in the smallest constellation, or 0 if there are
+    /// none.
+    pub smallest: usize,
+    /// The number of constellations of each size, keyed by size.
+    pub histogram: BTreeMap<usize, usize>,
+    /// A summary of all points together, or `None` if there are no points.
+    pub overall: Option<Summary<T>>,
+    /// A summary of each constellation, in the same order as the
+    /// constellations.
+    pub groups: Vec<Summary<T>>,
+}
+
+impl<T: Coordinate> Stats<T> {
+    /// Compute statistics about `consts`. This fails if a diameter does not
+    /// fit in a coordinate.
+    pub fn new(consts: &Constellations<T>) -> Result<Stats<T>> {
+        let groups = consts
+            .groups()
+            .iter()
+            .map(|g| g.summary())
+            .collect::<Result<Vec<Summary<T>>>>()?;
+        let mut histogram = BTreeMap::new();
+        for g in &groups {
+            *histogram.entry(g.size).or_insert(0) += 1;
+        }
+        let all: Vec<&Point<T>> = consts.groups().iter().flat_map(|g| g.points()).collect();
+        Ok(Stats {
+            points: all.len(),
+            constellations: groups.len(),
+            singletons: histogram.get(&1).cloned().unwrap_or(0),
+            largest: histogram.keys().next_back().cloned().unwrap_or(0),
+            smallest: histogram.keys().next().cloned().unwrap_or(0),
+            histogram,
+            overall: Summary::of(all)?,
+            groups,
+        })
+    }
+}
+
+/// The size and extent of a non-empty set of points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary<T = i64> {
+    /// The number of points.
+    pub size: usize,
+    /// The smallest coordinate on each axis. Together with `max`, this is the
+    /// corner of the axis-aligned bounding box.
+    pub min: Point<T>,
+    /// The largest coordinate on each axis.
+    pub max: Point<T>,
+    /// The mean of the points.
+    pub centroid: Vec<f64>,
+    /// The largest Manhattan distance between any two of the points.
+    pub diameter: T,
+}
+
+impl<T: Coordinate> Summary<T> {
+    /// Summarize `points`, which must all have the same number of coordinates.
+    /// Returns `None` if there are no points, and fails if their diameter
+    /// does not fit in a coordinate.
+    pub fn of<'a, I>(points: I) -> Result<Option<Summary<T>>>
+    where
+        T: 'a,
+        I: IntoIterator<Item = &'a Point<T>>,
+        I::IntoIter: Clone,
+    {
+        let points = points.into_iter();
+        let Some(first) = points.clone().next() else {
+            return Ok(None);
+        };
+        let dimension = first.dimension();
+        let mut min = first.coords().to_vec();
+        let mut max = first.coords().to_vec();
+        // Sums of integer coordinates can't overflow 128 bits in practice,
+        // but they are checked all the same.
+        let mut sum = vec![T::Wide::ZERO; dimension];
+        let mut size = 0;
+        for p in points.clone() {
+            for (axis, &c) in p.coords().iter().enumerate() {
+                if c < min[axis] {
+                    min[axis] = c;
+                }
+                if c > max[axis] {
+                    max[axis] = c;
+                }
+                sum[axis] = sum[axis]
+                    .checked_add(c.widen())
+                    .ok_or_else(|| Error::Overflow("a centroid".to_string()))?;
+            }
+            size += 1;
+        }
+        let diameter = diameter(points, size, dimension)?;
+        Ok(Some(Summary {
+            size,
+            min: Point::new(min),
+            max: Point::new(max),
+            centroid: sum.iter().map(|&s| s.to_f64() / size as f64).collect(),
+            diameter,
+        }))
+    }
+}
+
+/// Return the largest Manhattan distance between any two of `points`.
+///
+/// The Manhattan distance between two points is the largest difference
+/// between their projections onto one of the 2^(d-1) directions whose
+/// components are all ±1, so the diameter takes a single pass over the points
+/// per direction. When there are fewer points than directions, comparing
+/// every pair is cheaper.
+fn diameter<'a, T, I>(points: I, size: usize, dimension: usize) -> Result<T>
+where
+    T: Coordinate + 'a,
+    I: Iterator<Item = &'a Point<T>> + Clone,
+{
+    let directions = match 1usize.checked_shl(dimension.saturating_sub(1) as u32) {
+        Some(n) if n <= size / 2 => n,
+        _ => return brute_force_diameter(points),
+    };
+
+    let overflow = || Error::Overflow("a diameter".to_string());
+    let mut diameter = T::Wide::ZERO;
+    for signs in 0..directions {
+        let mut range: Option<(T::Wide, T::Wide)> = None;
+        for p in points.clone() {
+            let mut projection = T::Wide::ZERO;
+            for (axis, &c) in p.coords().iter().enumerate() {
+                // The first axis always counts positively, since flipping
+                // every sign gives the same distances.
+                projection = if axis > 0 && signs & (1 << (axis - 1)) != 0 {
+                    projection.checked_sub(c.widen())
+                } else {
+                    projection.checked_add(c.widen())
+                }
+                .ok_or_else(overflow)?;
+            }
+            range = match range {
+                None => Some((projection, projection)),
+                Some((lo, hi)) if projection < lo => Some((projection, hi)),
+                Some((lo, hi)) if projection > hi => Some((lo, projection)),
+                range => range,
+            };
+        }
+        if let Some((lo, hi)) = range {
+            let width = hi.checked_sub(lo).ok_or_else(overflow)?;
+            if width > diameter {
+                diameter = width;
+            }
+        }
+    }
+    T::narrow(diameter).ok_or_else(overflow)
+}
+
+fn brute_force_diameter<'a, T, I>(points: I) -> Result<T>
+where
+    T: Coordinate + 'a,
+    I: Iterator<Item = &'a Point<T>> + Clone,
+{
+    let mut diameter = T::from(0);
+    for (i, a) in points.clone().enumerate() {
+        for b in points.clone().skip(i + 1) {
+            let distance = a.distance(b)?;
+            if distance > diameter {
+                diameter = distance;
+            }
+        }
+    }
+    Ok(diameter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Threshold;
+
+    /// A xorshift generator, so that the tests are repeatable and need no
+    /// dependencies.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn point(&mut self, dimension: usize) -> Point {
+            Point::new(
+                (0..dimension)
+                    .map(|_| (self.next() % 2001) as i64 - 1000)
+                    .collect(),
+            )
+        }
+    }
+
+    fn points(coords: &[&[i64]]) -> Vec<Point> {
+        coords.iter().map(|c| Point::new(c.to_vec())).collect()
+    }
+
+    #[test]
+    fn diameter_matches_brute_force() {
+        let mut rng = Rng(0x853c49e6748fea9b);
+        for dimension in 1..=5 {
+            // Projection is used once there are at least twice as many
+            // points as directions.
+            let cutoff = 2 << (dimension - 1);
+            for size in [1, 2, 3, cutoff - 1, cutoff, cutoff + 1, 100] {
+                let points: Vec<Point> = (0..size).map(|_| rng.point(dimension)).collect();
+                assert_eq!(
+                    diameter(points.iter(), size, dimension).unwrap(),
+                    brute_force_diameter(points.iter()).unwrap(),
+                    "{} points with {} coordinates",
+                    size,
+                    dimension
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn diameter_overflow() {
+        let points = points(&[&[i64::MIN, 0], &[i64::MAX, 0], &[0, 0], &[0, 1]]);
+        assert!(matches!(
+            diameter(points.iter(), 4, 2),
+            Err(Error::Overflow(_))
+        ));
+        assert!(matches!(Summary::of(&points), Err(Error::Overflow(_))));
+    }
+
+    #[test]
+    fn summary_of_fixed_points() {
+        let points = points(&[&[0, 0], &[2, -1], &[4, 5]]);
+        let summary = Summary::of(&points).unwrap().unwrap();
+        assert_eq!(summary.size, 3);
+        assert_eq!(summary.min, Point::new(vec![0, -1]));
+        assert_eq!(summary.max, Point::new(vec![4, 5]));
+        assert_eq!(summary.centroid, vec![2.0, 4.0 / 3.0]);
+        assert_eq!(summary.diameter, 9);
+
+        assert_eq!(Summary::<i64>::of(&[]).unwrap(), None);
+    }
+
+    #[test]
+    fn stats_histogram_and_singletons() {
+        let points = points(&[&[0], &[1], &[10], &[20], &[21], &[22], &[40]]);
+        let stats = Stats::new(&Constellations::cluster(&points, Threshold::default())).unwrap();
+        assert_eq!(stats.points, 7);
+        assert_eq!(stats.constellations, 4);
+        assert_eq!(stats.singletons, 2);
+        assert_eq!(stats.largest, 3);
+        assert_eq!(stats.smallest, 1);
+        assert_eq!(stats.histogram, BTreeMap::from([(1, 2), (2, 1), (3, 1)]));
+        let overall = stats.overall.unwrap();
+        assert_eq!((overall.size, overall.diameter), (7, 40));
+        let sizes: Vec<usize> = stats.groups.iter().map(|g| g.size).collect();
+        assert_eq!(sizes, vec![2, 1, 3, 1]);
+
+        let empty = Stats::new(&Constellations::<i64>::cluster(&[], Threshold::default())).unwrap();
+        assert_eq!((empty.points, empty.largest, empty.smallest), (0, 0, 0));
+        assert_eq!(empty.overall, None);
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+/// A disjoint-set forest with path compression and union by rank.
+#[derive(Clone, Debug)]
+pub(crate) struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    size: Vec<usize>,
+    /// The next element of the same set. Following it from any element
+    /// visits the whole set and comes back around.
+    next: Vec<usize>,
+    sets: usize,
+}
+
+impl UnionFind {
+    /// Create `len` singleton sets, numbered `0..len`.
+    pub(crate) fn new(len: usize) -> UnionFind {
+        UnionFind {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+            size: vec![1; len],
+            next: (0..len).collect(),
+            sets: len,
+        }
+    }
+
+    /// Return the number of disjoint sets.
+    pub(crate) fn sets(&self) -> usize {
+        self.sets
+    }
+
+    /// Return the number of elements in the set containing `x`.
+    pub(crate) fn size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+
+    /// Return every element of the set containing `x`, starting with `x`.
+    /// This takes time in proportion to the size of the set.
+    pub(crate) fn members(&self, x: usize) -> Vec<usize> {
+        let mut members = vec![x];
+        let mut cur = self.next[x];
+        while cur != x {
+            members.push(cur);
+            cur = self.next[cur];
+        }
+        members
+    }
+
+    /// Add a new singleton set and return its number.
+    pub(crate) fn push(&mut self) -> usize {
+        self.parent.push(self.parent.len());
+        self.rank.push(0);
+        self.size.push(1);
+        self.next.push(self.parent.len() - 1);
+        self.sets += 1;
+        self.parent.len() - 1
+    }
+
+    /// Return the representative of the set containing `x`.
+    pub(crate) fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut cur = x;
+        while self.parent[cur] != root {
+            let next = self.parent[cur];
+            self.parent[cur] = root;
+            cur = next;
+        }
+        root
+    }
+
+    /// Split a set back into singletons. `members` must be every element of
+    /// one set.
+    ///
+    /// This is safe because no element outside a set ever points into it, so
+    /// only the members themselves need to be reset.
+    pub(crate) fn split(&mut self, members: &[usize]) {
+        for &x in members {
+            self.parent[x] = x;
+            self.rank[x] = 0;
+            self.size[x] = 1;
+            self.next[x] = x;
+        }
+        self.sets += members.len().saturating_sub(1);
+    }
+
+    /// Merge the sets containing `a` and `b`. Returns false if they were
+    /// already in the same set.
+    pub(crate) fn union(&mut self, a: usize, b: usize) -> bool {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return false;
+        }
+        let (child, root) = if self.rank[ra] < self.rank[rb] {
+            (ra, rb)
+        } else {
+            (rb, ra)
+        };
+        if self.rank[ra] == self.rank[rb] {
+            self.rank[root] += 1;
+        }
+        self.parent[child] = root;
+        self.size[root] += self.size[child];
+        // Swapping successors joins the two cycles into one.
+        self.next.swap(ra, rb);
+        self.sets -= 1;
+        true
+    }
+}
+
+/// A disjoint-set forest over a few elements out of a large range of numbers.
+///
+/// Every number starts out as a singleton set, but only the elements that
+/// have been merged take up any space.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SparseUnionFind {
+    /// The parent and rank of each merged element. Elements with no entry
+    /// are singletons.
+    nodes: HashMap<usize, (usize, u8)>,
+}
+
+impl SparseUnionFind {
+    /// Return the representative of the set containing `x`.
+    pub(crate) fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while let Some(&(parent, _)) = self.nodes.get(&root) {
+            if parent == root {
+                break;
+            }
+            root = parent;
+        }
+        let mut cur = x;
+        while cur != root {
+            let node = self.nodes.get_mut(&cur).unwrap();
+            cur = node.0;
+            node.0 = root;
+        }
+        root
+    }
+
+    /// Merge the sets containing `a` and `b`. Returns false if they were
+    /// already in the same set.
+    pub(crate) fn union(&mut self, a: usize, b: usize) -> bool {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return false;
+        }
+        let rank_a = self.nodes.entry(ra).or_insert((ra, 0)).1;
+        let rank_b = self.nodes.entry(rb).or_insert((rb, 0)).1;
+        let (child, root) = if rank_a < rank_b { (ra, rb) } else { (rb, ra) };
+        self.nodes.get_mut(&child).unwrap().0 = root;
+        if rank_a == rank_b {
+            self.nodes.get_mut(&root).unwrap().1 += 1;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn members_follow_unions() {
+        let mut uf = UnionFind::new(5);
+        assert_eq!(uf.members(3), vec![3]);
+        uf.union(0, 3);
+        uf.union(4, 1);
+        uf.union(3, 1);
+        let mut members = uf.members(4);
+        assert_eq!(members[0], 4);
+        members.sort_unstable();
+        assert_eq!(members, vec![0, 1, 3, 4]);
+        assert_eq!(uf.members(2), vec![2]);
+        let x = uf.push();
+        assert_eq!(uf.members(x), vec![x]);
+    }
+
+    #[test]
+    fn split_restores_singletons() {
+        let mut uf = UnionFind::new(6);
+        uf.union(0, 1);
+        uf.union(1, 2);
+        uf.union(3, 4);
+        assert_eq!(uf.sets(), 3);
+
+        uf.split(&[0, 1, 2]);
+        assert_eq!(uf.sets(), 5);
+        for x in 0..3 {
+            assert_eq!(uf.find(x), x);
+            assert_eq!(uf.size(x), 1);
+        }
+        assert_eq!(uf.size(4), 2);
+        assert_eq!(uf.members(1), vec![1]);
+
+        assert!(uf.union(2, 0));
+        assert!(!uf.union(0, 2));
+        assert_eq!(uf.size(0), 2);
+        assert_eq!(uf.sets(), 4);
+    }
+
+    #[test]
+    fn sparse_matches_dense() {
+        let pairs = [
+            (7, 900),
+            (900, 3),
+            (12, 40),
+            (3, 7),
+            (40, 1000),
+            (999, 12),
+            (5, 5),
+        ];
+        let mut dense = UnionFind::new(1001);
+        let mut sparse = SparseUnionFind::default();
+        for (a, b) in pairs {
+            assert_eq!(sparse.union(a, b), dense.union(a, b));
+        }
+        for a in [3, 5, 7, 12, 40, 500, 900, 999, 1000] {
+            for b in [3, 5, 7, 12, 40, 500, 900, 999, 1000] {
+                let same = dense.find(a) == dense.find(b);
+                assert_eq!(sparse.find(a) == sparse.find(b), same, "{} {}", a, b);
+            }
+        }
+        assert_eq!(sparse.nodes.len(), 7);
+    }
+}